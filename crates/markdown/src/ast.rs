@@ -0,0 +1,118 @@
+use crate::toc::TocEntry;
+
+/// A parsed markdown document: a tree of blocks, which hold inlines. Link
+/// references are already resolved, so every link carries its own URL.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading {
+        level: u8,
+        /// The anchor to link to: a `{#custom-id}` given after the heading
+        /// text, or else the slug of the text.
+        id: Option<String>,
+        content: Vec<Inline>,
+    },
+    Paragraph(Vec<Inline>),
+    CodeBlock {
+        language: Option<String>,
+        code: String,
+    },
+    List(List),
+    BlockQuote(Vec<Block>),
+    /// A GitHub-style `> [!NOTE]` callout.
+    Admonition {
+        kind: String,
+        title: String,
+        blocks: Vec<Block>,
+    },
+    Table(Table),
+    Html(String),
+    ThematicBreak,
+    /// A `[TOC]` or `[[_TOC_]]` placeholder, holding the table of contents of
+    /// the whole document.
+    TableOfContents(Vec<TocEntry>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct List {
+    pub kind: ListKind,
+    /// Items of tight lists aren't separated by blank lines, and their
+    /// paragraphs are rendered without `<p>` tags.
+    pub tight: bool,
+    pub items: Vec<Vec<Block>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListKind {
+    Unordered,
+    /// An ordered list with the number of its first item.
+    Ordered(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub alignments: Vec<Alignment>,
+    pub header: Vec<Vec<Inline>>,
+    pub rows: Vec<Vec<Vec<Inline>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    None,   // ---
+    Left,   // :---
+    Center, // :---:
+    Right,  // ---:
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Link {
+        url: String,
+        title: Option<String>,
+        content: Vec<Inline>,
+    },
+    Image {
+        url: String,
+        title: Option<String>,
+        alt: Vec<Inline>,
+    },
+    /// A `[[Page#Heading|label]]` link, resolved when rendering.
+    WikiLink {
+        target: String,
+        label: Option<String>,
+    },
+    Html(String),
+    SoftBreak,
+    HardBreak,
+}
+
+/// Returns the text of inlines without any formatting, as used for image alt
+/// text, heading slugs and search.
+pub fn plain_text(content: &[Inline]) -> String {
+    let mut text = String::new();
+    push_plain_text(&mut text, content);
+    text
+}
+
+fn push_plain_text(text: &mut String, content: &[Inline]) {
+    for inline in content {
+        match inline {
+            Inline::Text(s) | Inline::Code(s) => text.push_str(s),
+            Inline::Emphasis(content)
+            | Inline::Strong(content)
+            | Inline::Link { content, .. }
+            | Inline::Image { alt: content, .. } => push_plain_text(text, content),
+            Inline::WikiLink { target, label } => text.push_str(label.as_ref().unwrap_or(target)),
+            Inline::Html(_) => (),
+            Inline::SoftBreak | Inline::HardBreak => text.push(' '),
+        }
+    }
+}
@@ -0,0 +1,716 @@
+use std::{collections::HashSet, fmt};
+
+/// What a page says about itself in its front matter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// Other names `[[Page]]` links can use for the page.
+    pub aliases: Vec<String>,
+    pub authors: Vec<String>,
+    /// Drafts are left out of the build.
+    pub draft: bool,
+    /// Where the page goes among its siblings, lowest first.
+    pub order: Option<i64>,
+    /// The layout to render the page with, instead of the default one.
+    pub layout: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatterError {
+    /// The line of the page the error is on, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FrontMatterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for FrontMatterError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Yaml,
+    Toml,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    String(String),
+    Bool(bool),
+    Integer(i64),
+    List(Vec<Value>),
+    /// Anything only unknown keys can use, such as dates and nested tables.
+    Other,
+}
+
+/// A key, its value and the line it is on.
+type Entry = (String, Value, usize);
+
+struct FrontMatter<'a> {
+    format: Format,
+    text: &'a str,
+    body: &'a str,
+}
+
+/// Finds front matter between `---` lines (YAML) or `+++` lines (TOML) at
+/// the very start of a page. Without a closing line there is none, and the
+/// first line is just markdown.
+fn split(markdown: &str) -> Option<FrontMatter<'_>> {
+    let markdown = markdown.strip_prefix('\u{FEFF}').unwrap_or(markdown);
+    let (first, rest) = markdown.split_once('\n')?;
+    let (format, closing): (_, &[&str]) = match first.trim_end() {
+        "---" => (Format::Yaml, &["---", "..."]),
+        "+++" => (Format::Toml, &["+++"]),
+        _ => return None,
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if closing.contains(&line.trim_end()) {
+            return Some(FrontMatter {
+                format,
+                text: &rest[..offset],
+                body: &rest[offset + line.len()..],
+            });
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The front matter of a page and its entries. A block that doesn't parse
+/// isn't front matter, so pages starting with a `---` thematic break, with a
+/// second one or a setext heading further down, are all markdown.
+fn front_matter(markdown: &str) -> Option<(FrontMatter<'_>, Vec<Entry>)> {
+    let front_matter = split(markdown)?;
+    // Line 1 is the opening `---` or `+++`.
+    let entries = match front_matter.format {
+        Format::Yaml => parse_yaml(front_matter.text, 2),
+        Format::Toml => parse_toml(front_matter.text, 2),
+    };
+    Some((front_matter, entries.ok()?))
+}
+
+/// Returns the markdown after the front matter, or all of it if there is
+/// none.
+pub fn strip_front_matter(markdown: &str) -> &str {
+    front_matter(markdown).map_or(markdown, |(front_matter, _)| front_matter.body)
+}
+
+/// Reads the front matter of a page. Keys other than the ones [`Metadata`]
+/// has are allowed and ignored, but known keys given twice or with values of
+/// the wrong type are errors. Pages without front matter get the default
+/// metadata.
+pub fn parse_front_matter(markdown: &str) -> Result<Metadata, FrontMatterError> {
+    let Some((_, entries)) = front_matter(markdown) else {
+        return Ok(Metadata::default());
+    };
+
+    let mut metadata = Metadata::default();
+    let mut seen = HashSet::new();
+    for (key, value, line) in entries {
+        if !seen.insert(key.clone()) {
+            return Err(error(line, format!("`{key}` is given twice")));
+        }
+        let expected = |what: &str| error(line, format!("`{key}` should be {what}"));
+        match key.as_str() {
+            "title" => metadata.title = optional_string(value).ok_or_else(|| expected("text"))?,
+            "description" => {
+                metadata.description = optional_string(value).ok_or_else(|| expected("text"))?;
+            }
+            "layout" => metadata.layout = optional_string(value).ok_or_else(|| expected("text"))?,
+            "tags" => metadata.tags = strings(value).ok_or_else(|| expected("a list"))?,
+            "aliases" => metadata.aliases = strings(value).ok_or_else(|| expected("a list"))?,
+            "authors" | "author" => {
+                let authors = strings(value).ok_or_else(|| expected("a list"))?;
+                metadata.authors.extend(authors);
+            }
+            "draft" => {
+                metadata.draft = match value {
+                    Value::Null => false,
+                    Value::Bool(draft) => draft,
+                    _ => return Err(expected("`true` or `false`")),
+                };
+            }
+            "order" => {
+                metadata.order = match value {
+                    Value::Null => None,
+                    Value::Integer(order) => Some(order),
+                    _ => return Err(expected("a whole number")),
+                };
+            }
+            _ => (),
+        }
+    }
+    Ok(metadata)
+}
+
+fn error(line: usize, message: impl Into<String>) -> FrontMatterError {
+    FrontMatterError {
+        line,
+        message: message.into(),
+    }
+}
+
+/// Text values, where numbers and booleans count as their text so that
+/// `title: 2024` works.
+fn string(value: Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s),
+        Value::Integer(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn optional_string(value: Value) -> Option<Option<String>> {
+    match value {
+        Value::Null => Some(None),
+        value => string(value).map(Some),
+    }
+}
+
+/// A list of text values, where a single value is a list of one.
+fn strings(value: Value) -> Option<Vec<String>> {
+    match value {
+        Value::Null => Some(Vec::new()),
+        Value::List(values) => values.into_iter().map(string).collect(),
+        value => string(value).map(|s| vec![s]),
+    }
+}
+
+/// Parses the subset of YAML front matter uses: `key: value` lines with
+/// plain, quoted or `|`/`>` block scalars, and `[a, b]` or `- a` lists.
+fn parse_yaml(text: &str, first_line: usize) -> Result<Vec<Entry>, FrontMatterError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let is_blank = |line: &str| {
+        let line = line.trim();
+        line.is_empty() || line.starts_with('#')
+    };
+
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let number = first_line + i;
+        i += 1;
+        if is_blank(line) {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            return Err(error(number, "unexpected indentation"));
+        }
+
+        let Some((key, value)) = split_yaml_key(line) else {
+            return Err(error(number, "expected `key: value`"));
+        };
+
+        // Indented lines, and `- ` items, belong to this key.
+        let start = i;
+        while i < lines.len()
+            && (is_blank(lines[i])
+                || lines[i].starts_with(char::is_whitespace)
+                || lines[i] == "-"
+                || lines[i].starts_with("- "))
+        {
+            i += 1;
+        }
+        let nested = &lines[start..i];
+        let nested_line = |j: usize| first_line + start + j;
+
+        let value = value.trim();
+        let value = if value.starts_with(['|', '>']) && is_block_indicator(value) {
+            block_scalar(value, nested)
+        } else if !value.is_empty() {
+            if let Some(j) = nested.iter().position(|line| !is_blank(line)) {
+                return Err(error(nested_line(j), "unexpected indentation"));
+            }
+            parse_yaml_value(value).map_err(|message| error(number, message))?
+        } else if let Some(j) = nested.iter().position(|line| !is_blank(line)) {
+            let items = nested[j..]
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| !is_blank(line));
+            let mut list = Vec::new();
+            for (k, item) in items {
+                let item = item.trim_start();
+                let Some(item) = item
+                    .strip_prefix('-')
+                    .filter(|item| item.is_empty() || item.starts_with(char::is_whitespace))
+                else {
+                    // A nested mapping, which no known key takes.
+                    list.clear();
+                    break;
+                };
+                let item = parse_yaml_value(item.trim())
+                    .map_err(|message| error(nested_line(j + k), message))?;
+                list.push(item);
+            }
+            if list.is_empty() {
+                Value::Other
+            } else {
+                Value::List(list)
+            }
+        } else {
+            Value::Null
+        };
+        entries.push((key.to_string(), value, number));
+    }
+    Ok(entries)
+}
+
+fn split_yaml_key(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = match line.split_once(": ") {
+        Some(split) => split,
+        None => (line.strip_suffix(':')?, ""),
+    };
+    let key = key.trim();
+    let key = match key.strip_prefix('"').and_then(|key| key.strip_suffix('"')) {
+        Some(quoted) => quoted,
+        None => key
+            .strip_prefix('\'')
+            .and_then(|key| key.strip_suffix('\''))
+            .unwrap_or(key),
+    };
+    (!key.is_empty()).then_some((key, value))
+}
+
+fn is_block_indicator(value: &str) -> bool {
+    let value = strip_comment(value);
+    value[1..]
+        .chars()
+        .all(|c| matches!(c, '-' | '+' | '1'..='9'))
+}
+
+/// A `|` block keeps its line breaks and a `>` block folds lines into one,
+/// like YAML's, with the trailing line break stripped.
+fn block_scalar(indicator: &str, lines: &[&str]) -> Value {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines = lines.iter().map(|line| line.get(indent..).unwrap_or(""));
+
+    let text = if indicator.starts_with('|') {
+        lines.collect::<Vec<_>>().join("\n")
+    } else {
+        let mut text = String::new();
+        for line in lines {
+            if line.is_empty() {
+                text.push('\n');
+            } else {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push(' ');
+                }
+                text.push_str(line);
+            }
+        }
+        text
+    };
+    Value::String(text.trim_end().to_string())
+}
+
+fn parse_yaml_value(value: &str) -> Result<Value, String> {
+    if value.starts_with('[') {
+        let (items, rest) = parse_list(value, parse_yaml_item)?;
+        return finish(Value::List(items), rest);
+    }
+    if value.starts_with(['"', '\'']) {
+        let (s, rest) = parse_quoted(value, value.starts_with('\''))?;
+        return finish(Value::String(s), rest);
+    }
+    Ok(plain_scalar(strip_comment(value)))
+}
+
+fn parse_yaml_item(value: &str) -> Result<(Value, &str), String> {
+    if value.starts_with(['"', '\'']) {
+        let (s, rest) = parse_quoted(value, value.starts_with('\''))?;
+        return Ok((Value::String(s), rest));
+    }
+    let end = value.find([',', ']']).unwrap_or(value.len());
+    Ok((plain_scalar(&value[..end]), &value[end..]))
+}
+
+fn plain_scalar(value: &str) -> Value {
+    match value.trim() {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        value => match value.parse() {
+            Ok(n) => Value::Integer(n),
+            Err(_) => Value::String(value.to_string()),
+        },
+    }
+}
+
+/// Parses the subset of TOML front matter uses: `key = value` lines with
+/// strings, booleans, integers and arrays. Keys under `[table]` headers
+/// aren't top-level ones, so they are skipped.
+fn parse_toml(text: &str, first_line: usize) -> Result<Vec<Entry>, FrontMatterError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut entries = Vec::new();
+    let mut in_table = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let number = first_line + i;
+        let line = lines[i].trim();
+        i += 1;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_table = true;
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(error(number, "expected `key = value`"));
+        };
+        let key = key.trim();
+        let key = key
+            .strip_prefix(['"', '\''])
+            .and_then(|key| key.strip_suffix(['"', '\'']))
+            .unwrap_or(key);
+
+        // Multi-line strings and arrays continue until they are closed.
+        let mut value = value.trim().to_string();
+        while !is_complete(&value) && i < lines.len() {
+            value.push('\n');
+            value.push_str(lines[i]);
+            i += 1;
+        }
+
+        let value = parse_toml_value(&value).map_err(|message| error(number, message))?;
+        if !in_table {
+            entries.push((key.to_string(), value, number));
+        }
+    }
+    Ok(entries)
+}
+
+/// Whether a TOML value has all its strings and arrays closed.
+fn is_complete(value: &str) -> bool {
+    for quotes in ["\"\"\"", "'''"] {
+        if let Some(rest) = value.strip_prefix(quotes) {
+            return rest.contains(quotes);
+        }
+    }
+    if !value.starts_with('[') {
+        return true;
+    }
+
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, '#') => {
+                chars.find(|&c| c == '\n');
+            }
+            (None, _) => (),
+        }
+    }
+    depth <= 0
+}
+
+fn parse_toml_value(value: &str) -> Result<Value, String> {
+    for quotes in ["\"\"\"", "'''"] {
+        if let Some(rest) = value.strip_prefix(quotes) {
+            let end = rest.find(quotes).ok_or("unterminated string")?;
+            let text = rest[..end].strip_prefix('\n').unwrap_or(&rest[..end]);
+            let text = if quotes == "'''" {
+                text.to_string()
+            } else {
+                unescape(text)?
+            };
+            return finish(Value::String(text), &rest[end + 3..]);
+        }
+    }
+    let (value, rest) = parse_toml_item(value)?;
+    finish(value, rest)
+}
+
+fn parse_toml_item(value: &str) -> Result<(Value, &str), String> {
+    if value.starts_with('[') {
+        let (items, rest) = parse_list(value, parse_toml_item)?;
+        return Ok((Value::List(items), rest));
+    }
+    if value.starts_with(['"', '\'']) {
+        let (s, rest) = parse_quoted(value, value.starts_with('\''))?;
+        return Ok((Value::String(s), rest));
+    }
+    if value.starts_with('{') {
+        let end = value.find('}').ok_or("unterminated inline table")?;
+        return Ok((Value::Other, &value[end + 1..]));
+    }
+
+    let end = value.find([',', ']', '#']).unwrap_or(value.len());
+    let item = match value[..end].trim() {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        item => match item.replace('_', "").parse() {
+            Ok(n) => Value::Integer(n),
+            Err(_) if item.is_empty() => return Err("expected a value".into()),
+            Err(_) => Value::Other,
+        },
+    };
+    Ok((item, &value[end..]))
+}
+
+/// Parses a `[a, b]` list, with items parsed by `parse_item`, returning it
+/// and the text after it.
+fn parse_list(
+    value: &str,
+    parse_item: fn(&str) -> Result<(Value, &str), String>,
+) -> Result<(Vec<Value>, &str), String> {
+    let mut items = Vec::new();
+    let mut rest = &value[1..];
+    loop {
+        rest = skip_list_whitespace(rest);
+        if let Some(after) = rest.strip_prefix(']') {
+            return Ok((items, after));
+        }
+        if rest.is_empty() {
+            return Err("unterminated list".into());
+        }
+
+        let (item, after) = parse_item(rest)?;
+        items.push(item);
+        rest = skip_list_whitespace(after);
+        match rest.strip_prefix(',') {
+            Some(after) => rest = after,
+            None if rest.starts_with(']') => (),
+            None => return Err("expected `,` or `]` in list".into()),
+        }
+    }
+}
+
+/// Skips whitespace, line breaks and comments between list items.
+fn skip_list_whitespace(mut text: &str) -> &str {
+    loop {
+        text = text.trim_start();
+        match text.strip_prefix('#') {
+            Some(comment) => text = comment.split_once('\n').map_or("", |(_, rest)| rest),
+            None => return text,
+        }
+    }
+}
+
+/// Parses a quoted string, returning it and the text after it. Single quoted
+/// strings have no escapes except YAML's `''` for a quote.
+fn parse_quoted(value: &str, single: bool) -> Result<(String, &str), String> {
+    let quote = if single { '\'' } else { '"' };
+    let mut escaped = false;
+    for (i, c) in value.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && !single {
+            escaped = true;
+        } else if c == quote {
+            let rest = &value[i + 1..];
+            if single && rest.starts_with('\'') {
+                continue;
+            }
+            let text = &value[1..i];
+            let text = if single {
+                text.replace("''", "'")
+            } else {
+                unescape(text)?
+            };
+            return Ok((text, rest));
+        }
+    }
+    Err("unterminated string".into())
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => output.push('\n'),
+            Some('t') => output.push('\t'),
+            Some('r') => output.push('\r'),
+            Some(c @ ('\\' | '"' | '/')) => output.push(c),
+            Some('\n') => {
+                // A line ending backslash joins it with the next line.
+                while chars.clone().next().is_some_and(char::is_whitespace) {
+                    chars.next();
+                }
+            }
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid escape `\\u{hex}`"))?;
+                output.push(c);
+            }
+            Some(c) => return Err(format!("invalid escape `\\{c}`")),
+            None => return Err("unterminated string".into()),
+        }
+    }
+    Ok(output)
+}
+
+/// Checks nothing but a comment follows a value.
+fn finish(value: Value, rest: &str) -> Result<Value, String> {
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(value)
+    } else {
+        Err(format!("unexpected `{rest}` after value"))
+    }
+}
+
+/// Strips a ` # comment` off a plain value.
+fn strip_comment(value: &str) -> &str {
+    let end = value
+        .match_indices('#')
+        .find(|(i, _)| *i == 0 || value[..*i].ends_with(char::is_whitespace))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn error_line(markdown: &str) -> usize {
+        parse_front_matter(markdown).unwrap_err().line
+    }
+
+    #[test]
+    fn yaml() {
+        let markdown = "---
+title: \"Deploying: the \\\"easy\\\" way\"
+description: >
+  Everything about
+  deploying.
+tags: [ops, 'on call', 2024]
+aliases:
+  - Deploy
+  - Shipping  # old name
+author: Sam
+draft: false
+order: -3
+layout: wide
+date: 2024-05-01
+extra:
+  nested: map
+---
+# Body
+";
+        let metadata = parse_front_matter(markdown).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata {
+                title: Some("Deploying: the \"easy\" way".into()),
+                description: Some("Everything about deploying.".into()),
+                tags: strings(&["ops", "on call", "2024"]),
+                aliases: strings(&["Deploy", "Shipping"]),
+                authors: strings(&["Sam"]),
+                draft: false,
+                order: Some(-3),
+                layout: Some("wide".into()),
+            }
+        );
+        assert_eq!(strip_front_matter(markdown), "# Body\n");
+    }
+
+    #[test]
+    fn toml() {
+        let markdown = "+++
+title = 'Runbook'   # comment
+tags = [
+  \"ops\",  # first
+  \"db\",
+]
+authors = [\"Ana\", \"Bo\"]
+draft = true
+order = 1_0
+date = 2024-05-01
+extra = { a = 1, b = [2] }
+description = \"\"\"
+Line one
+line two\"\"\"
+
+[params]
+title = \"not the page title\"
++++
+Body";
+        let metadata = parse_front_matter(markdown).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata {
+                title: Some("Runbook".into()),
+                description: Some("Line one\nline two".into()),
+                tags: strings(&["ops", "db"]),
+                aliases: vec![],
+                authors: strings(&["Ana", "Bo"]),
+                draft: true,
+                order: Some(10),
+                layout: None,
+            }
+        );
+        assert_eq!(strip_front_matter(markdown), "Body");
+    }
+
+    #[test]
+    fn no_front_matter() {
+        for markdown in [
+            "# Title\n---\n",
+            "---\ntitle: x\n",
+            " ---\na: b\n---\n",
+            "",
+            "---\nA paragraph\n---\n",
+            "---\n\nText\n\n---\n",
+            "---\ntitle: \"open\n---\n",
+            "---\ntags: [a, b\n---\n",
+            "---\ntitle: a\n  b: c\n---\n",
+            "+++\ntitle = \"a\" b\n+++\n",
+        ] {
+            assert_eq!(parse_front_matter(markdown), Ok(Metadata::default()));
+            assert_eq!(strip_front_matter(markdown), markdown);
+        }
+        assert_eq!(strip_front_matter("---\n---\nBody"), "Body");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_front_matter("---\ntitle: a\ndraft: maybe\n---\n"),
+            Err(FrontMatterError {
+                line: 3,
+                message: "`draft` should be `true` or `false`".into(),
+            })
+        );
+        assert_eq!(error_line("---\n\norder: first\n---\n"), 3);
+        assert_eq!(error_line("---\ntitle: a\ntitle: b\n---\n"), 3);
+        assert_eq!(error_line("+++\n\ntags = \"a\"\norder = \"x\"\n+++\n"), 4);
+    }
+}
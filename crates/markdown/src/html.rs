@@ -1,341 +1,753 @@
-use crate::lexer::Token;
+use crate::{
+    ast::{plain_text, Alignment, Block, Document, Inline, List, ListKind, Table},
+    sanitize::{escape_attribute, escape_html, is_safe_url, sanitize_html},
+    slug::slugify,
+    toc::TocEntry,
+    url::{encode_url, rewrite_markdown_link},
+    Options, RawHtml,
+};
 
-pub fn render_html(tokens: Vec<Token>) -> String {
+pub fn render_html(document: &Document, options: &Options) -> String {
     let mut html = String::new();
-    let mut h1 = false;
-    let mut h2 = false;
-    let mut h3 = false;
-    let mut h4 = false;
-    let mut h5 = false;
-    let mut h6 = false;
-    let mut ul = 0;
-    let mut li = false;
-    let mut p = false;
-    let mut bold = false;
-    let mut italic = false;
-    let mut should_start_with_space = false;
-
-    for token in tokens {
-        match token {
-            Token::Text(text) => {
-                if !li && !h1 && !h2 && !h3 && !h4 && !h5 && !h6 && !p {
-                    html.push_str("<p>");
-                    p = true;
-                }
-                if should_start_with_space {
-                    html.push(' ');
-                    should_start_with_space = false;
-                }
-                html.push_str(&text);
+    push_blocks(&mut html, &document.blocks, options);
+    html
+}
+
+fn push_blocks(html: &mut String, blocks: &[Block], options: &Options) {
+    for block in blocks {
+        push_block(html, block, options, false);
+    }
+}
+
+/// Renders a block. Paragraphs directly inside the items of a tight list are
+/// rendered without `<p>` tags.
+fn push_block(html: &mut String, block: &Block, options: &Options, tight: bool) {
+    match block {
+        Block::Heading { level, id, content } => {
+            match id {
+                Some(id) => html.push_str(&format!("<h{level} id=\"{}\">", escape_attribute(id))),
+                None => html.push_str(&format!("<h{level}>")),
             }
-            Token::Code(code) => {
-                if !li && !h1 && !h2 && !h3 && !h4 && !h5 && !h6 && !p {
-                    html.push_str("<p>");
-                    p = true;
-                }
-                html.push_str("<code>");
-                html.push_str(&code);
-                html.push_str("</code>");
+            push_inlines(html, content, options);
+            if let (Some(id), true) = (id, options.heading_anchors) {
+                html.push_str("<a class=\"anchor\" href=\"#");
+                html.push_str(&escape_attribute(id));
+                html.push_str("\" aria-label=\"Link to this section\">#</a>");
             }
-            Token::CodeBlock(language, code) => {
-                html.push_str("<pre>");
-                if let Some(lang) = language {
-                    html.push_str("<code class=\"language-");
-                    html.push_str(&lang);
-                    html.push_str("\">");
-                } else {
-                    html.push_str("<code>");
-                }
-                html.push_str(&code);
-                html.push_str("</code></pre>");
+            html.push_str(&format!("</h{level}>"));
+        }
+        Block::Paragraph(content) => {
+            if tight {
+                push_inlines(html, content, options);
+            } else {
+                html.push_str("<p>");
+                push_inlines(html, content, options);
+                html.push_str("</p>");
             }
-            Token::Heading1 => {
-                html.push_str("<h1>");
-                h1 = true;
+        }
+        Block::CodeBlock { language, code } => {
+            html.push_str("<pre>");
+            if let Some(lang) = language {
+                html.push_str("<code class=\"language-");
+                html.push_str(&escape_attribute(lang));
+                html.push_str("\">");
+            } else {
+                html.push_str("<code>");
             }
-            Token::Heading2 => {
-                html.push_str("<h2>");
-                h2 = true;
+            html.push_str(&escape_html(code));
+            html.push_str("</code></pre>");
+        }
+        Block::List(list) => push_list(html, list, options),
+        Block::BlockQuote(blocks) => {
+            html.push_str("<blockquote>");
+            push_blocks(html, blocks, options);
+            html.push_str("</blockquote>");
+        }
+        Block::Admonition {
+            kind,
+            title,
+            blocks,
+        } => {
+            html.push_str("<div class=\"admonition ");
+            html.push_str(&escape_attribute(kind));
+            html.push_str("\"><p class=\"admonition-title\">");
+            html.push_str(&escape_html(title));
+            html.push_str("</p>");
+            push_blocks(html, blocks, options);
+            html.push_str("</div>");
+        }
+        Block::Table(table) => push_table(html, table, options),
+        Block::Html(raw) => {
+            if options.raw_html == RawHtml::Escape {
+                html.push_str("<p>");
+                push_raw_html(html, raw, options);
+                html.push_str("</p>");
+            } else {
+                // The line break ending the block matters when the HTML
+                // leaves a `<pre>` open.
+                push_raw_html(html, raw, options);
+                html.push('\n');
             }
-            Token::Heading3 => {
-                html.push_str("<h3>");
-                h3 = true;
+        }
+        Block::ThematicBreak => html.push_str("<hr>"),
+        Block::TableOfContents(entries) => html.push_str(&render_toc(entries)),
+    }
+}
+
+/// Renders a table of contents as nested lists of links to the headings, in
+/// a `<nav class="toc">`. An empty one renders as nothing.
+pub fn render_toc(entries: &[TocEntry]) -> String {
+    let mut html = String::new();
+    if !entries.is_empty() {
+        html.push_str("<nav class=\"toc\">");
+        push_toc_entries(&mut html, entries);
+        html.push_str("</nav>");
+    }
+    html
+}
+
+fn push_toc_entries(html: &mut String, entries: &[TocEntry]) {
+    html.push_str("<ul>");
+    for entry in entries {
+        html.push_str("<li><a href=\"#");
+        html.push_str(&escape_attribute(&entry.id));
+        html.push_str("\">");
+        html.push_str(&escape_html(&entry.text));
+        html.push_str("</a>");
+        if !entry.children.is_empty() {
+            push_toc_entries(html, &entry.children);
+        }
+        html.push_str("</li>");
+    }
+    html.push_str("</ul>");
+}
+
+fn push_list(html: &mut String, list: &List, options: &Options) {
+    match list.kind {
+        ListKind::Unordered => html.push_str("<ul>"),
+        ListKind::Ordered(1) => html.push_str("<ol>"),
+        ListKind::Ordered(start) => html.push_str(&format!("<ol start=\"{start}\">")),
+    }
+
+    for item in &list.items {
+        html.push_str("<li>");
+        for block in item {
+            push_block(html, block, options, list.tight);
+        }
+        html.push_str("</li>");
+    }
+
+    match list.kind {
+        ListKind::Unordered => html.push_str("</ul>"),
+        ListKind::Ordered(_) => html.push_str("</ol>"),
+    }
+}
+
+fn push_table(html: &mut String, table: &Table, options: &Options) {
+    html.push_str("<table><thead><tr>");
+    for (cell, alignment) in table.header.iter().zip(&table.alignments) {
+        push_table_cell(html, "th", *alignment, cell, options);
+    }
+    html.push_str("</tr></thead>");
+
+    if !table.rows.is_empty() {
+        html.push_str("<tbody>");
+        for row in &table.rows {
+            html.push_str("<tr>");
+            for (cell, alignment) in row.iter().zip(&table.alignments) {
+                push_table_cell(html, "td", *alignment, cell, options);
             }
-            Token::Heading4 => {
-                html.push_str("<h4>");
-                h4 = true;
+            html.push_str("</tr>");
+        }
+        html.push_str("</tbody>");
+    }
+    html.push_str("</table>");
+}
+
+fn push_table_cell(
+    html: &mut String,
+    tag: &str,
+    alignment: Alignment,
+    cell: &[Inline],
+    options: &Options,
+) {
+    html.push('<');
+    html.push_str(tag);
+    match alignment {
+        Alignment::None => (),
+        Alignment::Left => html.push_str(" align=\"left\""),
+        Alignment::Center => html.push_str(" align=\"center\""),
+        Alignment::Right => html.push_str(" align=\"right\""),
+    }
+    html.push('>');
+    push_inlines(html, cell, options);
+    html.push_str("</");
+    html.push_str(tag);
+    html.push('>');
+}
+
+fn push_inlines(html: &mut String, inlines: &[Inline], options: &Options) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => html.push_str(&escape_html(text)),
+            Inline::Code(code) => {
+                html.push_str("<code>");
+                html.push_str(&escape_html(code));
+                html.push_str("</code>");
             }
-            Token::Heading5 => {
-                html.push_str("<h5>");
-                h5 = true;
+            Inline::Emphasis(content) => {
+                html.push_str("<em>");
+                push_inlines(html, content, options);
+                html.push_str("</em>");
             }
-            Token::Heading6 => {
-                html.push_str("<h6>");
-                h6 = true;
+            Inline::Strong(content) => {
+                html.push_str("<strong>");
+                push_inlines(html, content, options);
+                html.push_str("</strong>");
             }
-            Token::UnorderedBullet(indent) => {
-                while ul > indent + 1 {
-                    html.push_str("</ul>");
-                    ul -= 1;
+            Inline::Link {
+                url,
+                title,
+                content,
+            } => {
+                html.push_str("<a");
+                if let Some(url) = safe_url(url, options) {
+                    html.push_str(" href=\"");
+                    html.push_str(&escape_attribute(&encode_url(&rewrite_markdown_link(url))));
+                    html.push('"');
                 }
-
-                while ul <= indent {
-                    html.push_str("<ul>");
-                    ul += 1;
-                }
-
-                html.push_str("<li>");
-                li = true;
+                push_title(html, title);
+                html.push('>');
+                push_inlines(html, content, options);
+                html.push_str("</a>");
             }
-            Token::Bold => {
-                if !li && !h1 && !h2 && !h3 && !h4 && !h5 && !h6 && !p {
-                    html.push_str("<p>");
-                    p = true;
+            Inline::Image { url, title, alt } => {
+                html.push_str("<img");
+                if let Some(url) = safe_url(url, options) {
+                    html.push_str(" src=\"");
+                    html.push_str(&escape_attribute(&encode_url(url)));
+                    html.push('"');
                 }
-                if !bold {
-                    html.push_str("<b>");
-                } else {
-                    html.push_str("</b>");
-                }
-                bold = !bold;
+                html.push_str(" alt=\"");
+                html.push_str(&escape_attribute(&plain_text(alt)));
+                html.push('"');
+                push_title(html, title);
+                html.push('>');
             }
-            Token::Italic => {
-                if !li && !h1 && !h2 && !h3 && !h4 && !h5 && !h6 && !p {
-                    html.push_str("<p>");
-                    p = true;
-                }
-                if !italic {
-                    html.push_str("<i>");
-                } else {
-                    html.push_str("</i>");
-                }
-                italic = !bold;
+            Inline::WikiLink { target, label } => {
+                push_wiki_link(html, target, label.as_deref(), options)
             }
-            Token::EndOfFile | Token::Newline | Token::DoubleNewline => {
-                if h1 {
-                    html.push_str("</h1>");
-                    h1 = false;
-                }
-                if h2 {
-                    html.push_str("</h2>");
-                    h2 = false;
-                }
-                if h3 {
-                    html.push_str("</h3>");
-                    h3 = false;
-                }
-                if h4 {
-                    html.push_str("</h4>");
-                    h4 = false;
-                }
-                if h5 {
-                    html.push_str("</h5>");
-                    h5 = false;
-                }
-                if h6 {
-                    html.push_str("</h6>");
-                    h6 = false;
-                }
-                if p && token == Token::Newline {
-                    should_start_with_space = true;
-                }
-                if p && (token == Token::DoubleNewline || token == Token::EndOfFile) {
-                    html.push_str("</p>");
-                    p = false;
-                }
-                if li {
-                    html.push_str("</li>");
-                    li = false;
-                }
-                if token == Token::DoubleNewline || token == Token::EndOfFile {
-                    while ul > 0 {
-                        html.push_str("</ul>");
-                        ul -= 1;
-                    }
-                }
+            Inline::Html(raw) => push_raw_html(html, raw, options),
+            Inline::SoftBreak => html.push('\n'),
+            Inline::HardBreak => html.push_str("<br>"),
+        }
+    }
+}
+
+fn push_title(html: &mut String, title: &Option<String>) {
+    if let Some(title) = title {
+        html.push_str(" title=\"");
+        html.push_str(&escape_attribute(title));
+        html.push('"');
+    }
+}
+
+/// Renders a `[[Page#Heading|label]]` link. Pages the resolver doesn't know
+/// about are rendered as red links without a `href`. A page with a `#` in its
+/// name, such as `[[C# Notes]]`, wins over a heading of another page, so the
+/// resolver is asked about the whole target first.
+fn push_wiki_link(html: &mut String, target: &str, label: Option<&str>, options: &Options) {
+    let resolve = |page: &str| options.wiki_link_resolver.and_then(|resolve| resolve(page));
+    let whole = match target.contains('#') && !target.starts_with('#') {
+        true => resolve(target.trim()),
+        false => None,
+    };
+    let (page, heading, url) = match (whole, target.split_once('#')) {
+        (Some(url), _) => (target, None, Some(url)),
+        (None, Some((page, heading))) => {
+            let page = page.trim();
+            let url = match page.is_empty() {
+                true => Some(String::new()),
+                false => resolve(page),
+            };
+            (page, Some(heading.trim()), url)
+        }
+        (None, None) => (target, None, resolve(target)),
+    };
+
+    match url {
+        Some(mut url) => {
+            if let Some(heading) = heading {
+                url.push('#');
+                url.push_str(&slugify(heading));
             }
-            _ => todo!("{:?}", token),
+            html.push_str("<a class=\"wiki-link\" href=\"");
+            html.push_str(&escape_attribute(&url));
+            html.push_str("\">");
+        }
+        None => {
+            html.push_str("<a class=\"wiki-link red-link\" title=\"");
+            html.push_str(&escape_attribute(page));
+            html.push_str(" (page does not exist)\">");
         }
     }
-    html
+    html.push_str(&escape_html(label.unwrap_or(target)));
+    html.push_str("</a>");
+}
+
+/// Returns the URL of a link or image, unless raw HTML isn't passed through
+/// and it could run script.
+fn safe_url<'a>(url: &'a str, options: &Options) -> Option<&'a str> {
+    match options.raw_html {
+        RawHtml::PassThrough => Some(url),
+        _ => is_safe_url(url).then_some(url),
+    }
+}
+
+fn push_raw_html(html: &mut String, raw: &str, options: &Options) {
+    match options.raw_html {
+        RawHtml::PassThrough => html.push_str(raw),
+        RawHtml::Escape => html.push_str(&escape_html(raw)),
+        RawHtml::Sanitize => html.push_str(&sanitize_html(raw)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.into())
+    }
+
+    fn paragraph(content: Vec<Inline>) -> Block {
+        Block::Paragraph(content)
+    }
+
+    fn render(blocks: Vec<Block>) -> String {
+        render_html(&Document { blocks }, &Options::default())
+    }
+
     #[test]
-    fn heading_1() {
-        let tokens = vec![
-            Token::Heading1,
-            Token::Text("Hello World".into()),
-            Token::Newline,
-            Token::EndOfFile,
-        ];
-        assert_eq!(render_html(tokens), "<h1>Hello World</h1>");
+    fn headings() {
+        for level in 1..=6 {
+            let heading = Block::Heading {
+                level,
+                id: None,
+                content: vec![text("Hello World")],
+            };
+            assert_eq!(
+                render(vec![heading]),
+                format!("<h{level}>Hello World</h{level}>")
+            );
+        }
     }
 
     #[test]
-    fn heading_2() {
-        let tokens = vec![
-            Token::Heading2,
-            Token::Text("Hello World".into()),
-            Token::Newline,
-            Token::EndOfFile,
-        ];
-        assert_eq!(render_html(tokens), "<h2>Hello World</h2>");
+    fn heading_anchors() {
+        let blocks = vec![Block::Heading {
+            level: 2,
+            id: Some("setup".into()),
+            content: vec![text("Setup")],
+        }];
+        assert_eq!(render(blocks.clone()), "<h2 id=\"setup\">Setup</h2>");
+
+        let options = Options {
+            heading_anchors: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_html(&Document { blocks }, &options),
+            "<h2 id=\"setup\">Setup<a class=\"anchor\" href=\"#setup\" aria-label=\"Link to this section\">#</a></h2>"
+        );
     }
 
     #[test]
-    fn heading_3() {
-        let tokens = vec![
-            Token::Heading3,
-            Token::Text("Hello World".into()),
-            Token::Newline,
-            Token::EndOfFile,
+    fn table_of_contents() {
+        let entry = |level, id: &str, text: &str, children| TocEntry {
+            level,
+            id: id.into(),
+            text: text.into(),
+            children,
+        };
+        let toc = vec![
+            entry(
+                1,
+                "setup",
+                "Setup",
+                vec![entry(2, "a-b", "<a> & <b>", vec![])],
+            ),
+            entry(1, "usage", "Usage", vec![]),
         ];
-        assert_eq!(render_html(tokens), "<h3>Hello World</h3>");
+        assert_eq!(
+            render(vec![Block::TableOfContents(toc)]),
+            [
+                "<nav class=\"toc\"><ul>",
+                "<li><a href=\"#setup\">Setup</a>",
+                "<ul><li><a href=\"#a-b\">&lt;a&gt; &amp; &lt;b&gt;</a></li></ul></li>",
+                "<li><a href=\"#usage\">Usage</a></li>",
+                "</ul></nav>",
+            ]
+            .join("")
+        );
+        assert_eq!(render(vec![Block::TableOfContents(vec![])]), "");
     }
 
     #[test]
-    fn heading_4() {
-        let tokens = vec![
-            Token::Heading4,
-            Token::Text("Hello World".into()),
-            Token::Newline,
-            Token::EndOfFile,
-        ];
-        assert_eq!(render_html(tokens), "<h4>Hello World</h4>");
+    fn p() {
+        assert_eq!(
+            render(vec![paragraph(vec![text("Hello World")])]),
+            "<p>Hello World</p>"
+        );
     }
 
     #[test]
-    fn heading_5() {
-        let tokens = vec![
-            Token::Heading5,
-            Token::Text("Hello World".into()),
-            Token::Newline,
-            Token::EndOfFile,
+    fn bold_and_italic() {
+        let content = vec![
+            text("Hello "),
+            Inline::Strong(vec![text("World"), Inline::Emphasis(vec![text("!")])]),
+            text(" "),
+            Inline::Emphasis(vec![text("Hola")]),
         ];
-        assert_eq!(render_html(tokens), "<h5>Hello World</h5>");
+        assert_eq!(
+            render(vec![paragraph(content)]),
+            "<p>Hello <strong>World<em>!</em></strong> <em>Hola</em></p>"
+        );
     }
 
     #[test]
-    fn heading_6() {
-        let tokens = vec![
-            Token::Heading6,
-            Token::Text("Hello World".into()),
-            Token::Newline,
-            Token::EndOfFile,
+    fn line_breaks() {
+        let blocks = vec![
+            paragraph(vec![
+                text("Hi"),
+                Inline::SoftBreak,
+                text("Hey"),
+                Inline::HardBreak,
+                text("Ho"),
+            ]),
+            paragraph(vec![text("Yo")]),
+            Block::ThematicBreak,
         ];
-        assert_eq!(render_html(tokens), "<h6>Hello World</h6>");
+        assert_eq!(render(blocks), "<p>Hi\nHey<br>Ho</p><p>Yo</p><hr>");
     }
 
     #[test]
-    fn p() {
-        let tokens = vec![
-            Token::Text("Hello World".into()),
-            Token::Newline,
-            Token::EndOfFile,
-        ];
-        assert_eq!(render_html(tokens), "<p>Hello World</p>");
+    fn inline_code() {
+        let content = vec![Inline::Code("Hello".into()), text(" World!")];
+        assert_eq!(
+            render(vec![paragraph(content)]),
+            "<p><code>Hello</code> World!</p>"
+        );
     }
 
     #[test]
-    fn bold() {
-        let tokens = vec![
-            Token::Text("Hello ".into()),
-            Token::Bold,
-            Token::Text("World!".into()),
-            Token::Bold,
-            Token::Newline,
-            Token::EndOfFile,
-        ];
-        assert_eq!(render_html(tokens), "<p>Hello <b>World!</b></p>");
+    fn code_block() {
+        let code_block = Block::CodeBlock {
+            language: Some("rust".into()),
+            code: "fn main() {\n    println!(\"Hello World!\");\n}".into(),
+        };
+        assert_eq!(render(vec![code_block]), "<pre><code class=\"language-rust\">fn main() {\n    println!(\"Hello World!\");\n}</code></pre>");
     }
 
     #[test]
-    fn italic() {
-        let tokens = vec![
-            Token::Text("Hello ".into()),
-            Token::Italic,
-            Token::Text("World!".into()),
-            Token::Italic,
-            Token::Newline,
-            Token::EndOfFile,
-        ];
-        assert_eq!(render_html(tokens), "<p>Hello <i>World!</i></p>");
+    fn code_block_no_language() {
+        let code_block = Block::CodeBlock {
+            language: None,
+            code: "Hello\nWorld!".into(),
+        };
+        assert_eq!(
+            render(vec![code_block]),
+            "<pre><code>Hello\nWorld!</code></pre>"
+        );
     }
 
     #[test]
-    fn newlines() {
-        let tokens = vec![
-            Token::Text("Hi".into()),
-            Token::Newline,
-            Token::Text("Hey".into()),
-            Token::DoubleNewline,
-            Token::Text("Yo".into()),
-            Token::Newline,
-            Token::EndOfFile,
-        ];
-        assert_eq!(render_html(tokens), "<p>Hi Hey</p><p>Yo</p>");
+    fn unordered_list() {
+        let nested = Block::List(List {
+            kind: ListKind::Unordered,
+            tight: true,
+            items: vec![vec![paragraph(vec![text("Hello")])]],
+        });
+        let list = Block::List(List {
+            kind: ListKind::Unordered,
+            tight: true,
+            items: vec![
+                vec![paragraph(vec![text("Hello")])],
+                vec![
+                    paragraph(vec![text("Hi "), Inline::Emphasis(vec![text("there")])]),
+                    nested,
+                ],
+                vec![paragraph(vec![text("Hola")])],
+            ],
+        });
+        assert_eq!(
+            render(vec![list]),
+            "<ul><li>Hello</li><li>Hi <em>there</em><ul><li>Hello</li></ul></li><li>Hola</li></ul>"
+        );
     }
 
     #[test]
-    fn inline_code() {
-        let tokens = vec![
-            Token::Code("Hello".into()),
-            Token::Text(" World!".into()),
-            Token::Newline,
-            Token::EndOfFile,
+    fn ordered_list() {
+        let ordered = |start: usize, items: Vec<Vec<Block>>| {
+            Block::List(List {
+                kind: ListKind::Ordered(start),
+                tight: true,
+                items,
+            })
+        };
+        let blocks = vec![
+            ordered(
+                3,
+                vec![
+                    vec![paragraph(vec![text("Three")])],
+                    vec![paragraph(vec![text("Four")])],
+                ],
+            ),
+            ordered(1, vec![vec![paragraph(vec![text("New list")])]]),
         ];
-        assert_eq!(render_html(tokens), "<p><code>Hello</code> World!</p>");
+        assert_eq!(
+            render(blocks),
+            [
+                "<ol start=\"3\"><li>Three</li><li>Four</li></ol>",
+                "<ol><li>New list</li></ol>",
+            ]
+            .join("")
+        );
     }
 
     #[test]
-    fn code_block() {
-        let tokens = vec![
-            Token::CodeBlock(
-                Some("rust".into()),
-                "fn main() {\n    println!(\"Hello World!\");\n}".into(),
-            ),
-            Token::Newline,
-            Token::EndOfFile,
+    fn loose_list() {
+        let list = Block::List(List {
+            kind: ListKind::Unordered,
+            tight: false,
+            items: vec![
+                vec![paragraph(vec![text("One")]), paragraph(vec![text("More")])],
+                vec![paragraph(vec![text("Two")])],
+            ],
+        });
+        assert_eq!(
+            render(vec![list]),
+            "<ul><li><p>One</p><p>More</p></li><li><p>Two</p></li></ul>"
+        );
+    }
+
+    #[test]
+    fn links() {
+        let content = vec![
+            text("Go "),
+            Inline::Link {
+                url: "/\"home\" page".into(),
+                title: Some("Home".into()),
+                content: vec![text("home")],
+            },
+            text(" or "),
+            Inline::Link {
+                url: "../guides/setup.md#install".into(),
+                title: None,
+                content: vec![Inline::Strong(vec![text("Setup")])],
+            },
         ];
-        assert_eq!(render_html(tokens), "<pre><code class=\"language-rust\">fn main() {\n    println!(\"Hello World!\");\n}</code></pre>");
+        assert_eq!(
+            render(vec![paragraph(content)]),
+            [
+                "<p>Go <a href=\"/%22home%22%20page\" title=\"Home\">home</a> or ",
+                "<a href=\"../guides/setup.html#install\"><strong>Setup</strong></a></p>",
+            ]
+            .join("")
+        );
     }
 
     #[test]
-    fn code_block_no_language() {
-        let tokens = vec![
-            Token::CodeBlock(None, "Hello\nWorld!".into()),
-            Token::Newline,
-            Token::EndOfFile,
+    fn images() {
+        let content = vec![Inline::Image {
+            url: "img/a b.png".into(),
+            title: Some("Arch".into()),
+            alt: vec![text("A "), Inline::Emphasis(vec![text("diagram")])],
+        }];
+        assert_eq!(
+            render(vec![paragraph(content)]),
+            "<p><img src=\"img/a%20b.png\" alt=\"A diagram\" title=\"Arch\"></p>"
+        );
+    }
+
+    #[test]
+    fn wiki_links() {
+        let resolve = |page: &str| match page {
+            "Setup" => Some("guides/setup.html".to_string()),
+            "C# Notes" => Some("C%23%20Notes.html".to_string()),
+            _ => None,
+        };
+        let options = Options {
+            wiki_link_resolver: Some(&resolve),
+            ..Default::default()
+        };
+        let wiki_link = |target: &str, label: Option<&str>| Inline::WikiLink {
+            target: target.into(),
+            label: label.map(Into::into),
+        };
+        let document = Document {
+            blocks: vec![paragraph(vec![
+                wiki_link("Setup#First Steps!", Some("start")),
+                text(" "),
+                wiki_link("Missing", None),
+                text(" "),
+                wiki_link("#Intro", None),
+                text(" "),
+                wiki_link("C# Notes", None),
+            ])],
+        };
+        assert_eq!(
+            render_html(&document, &options),
+            [
+                "<p><a class=\"wiki-link\" href=\"guides/setup.html#first-steps\">start</a> ",
+                "<a class=\"wiki-link red-link\" title=\"Missing (page does not exist)\">Missing</a> ",
+                "<a class=\"wiki-link\" href=\"#intro\">#Intro</a> ",
+                "<a class=\"wiki-link\" href=\"C%23%20Notes.html\">C# Notes</a></p>",
+            ]
+            .join("")
+        );
+    }
+
+    #[test]
+    fn block_quote() {
+        let blocks = vec![
+            paragraph(vec![text("Before")]),
+            Block::BlockQuote(vec![
+                paragraph(vec![text("Quoted")]),
+                Block::BlockQuote(vec![paragraph(vec![text("Nested")])]),
+            ]),
         ];
-        assert_eq!(render_html(tokens), "<pre><code>Hello\nWorld!</code></pre>");
+        assert_eq!(
+            render(blocks),
+            [
+                "<p>Before</p><blockquote><p>Quoted</p>",
+                "<blockquote><p>Nested</p></blockquote></blockquote>",
+            ]
+            .join("")
+        );
     }
 
     #[test]
-    fn unordered_list() {
-        let tokens = vec![
-            Token::UnorderedBullet(0),
-            Token::Text("Hello".into()),
-            Token::Newline,
-            Token::UnorderedBullet(0),
-            Token::Text("Hi ".into()),
-            Token::Italic,
-            Token::Text("there".into()),
-            Token::Italic,
-            Token::Newline,
-            Token::UnorderedBullet(1),
-            Token::Text("Hello".into()),
-            Token::Newline,
-            Token::UnorderedBullet(0),
-            Token::Text("Hola".into()),
-            Token::EndOfFile,
+    fn admonition() {
+        let admonition = Block::Admonition {
+            kind: "warning".into(),
+            title: "Warning".into(),
+            blocks: vec![paragraph(vec![text("Careful")])],
+        };
+        assert_eq!(
+            render(vec![admonition]),
+            "<div class=\"admonition warning\"><p class=\"admonition-title\">Warning</p><p>Careful</p></div>"
+        );
+    }
+
+    #[test]
+    fn table() {
+        let table = Block::Table(Table {
+            alignments: vec![Alignment::None, Alignment::Center],
+            header: vec![vec![text("Key")], vec![text("Value")]],
+            rows: vec![vec![
+                vec![Inline::Code("port".into())],
+                vec![Inline::Strong(vec![text("8080")])],
+            ]],
+        });
+        assert_eq!(
+            render(vec![table]),
+            [
+                "<table><thead><tr><th>Key</th><th align=\"center\">Value</th></tr></thead>",
+                "<tbody><tr><td><code>port</code></td><td align=\"center\"><strong>8080</strong></td></tr></tbody>",
+                "</table>",
+            ]
+            .join("")
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        let blocks = vec![
+            paragraph(vec![
+                text("1 < 2 & 3 > 2 &copy;"),
+                Inline::Code("<div>".into()),
+            ]),
+            Block::CodeBlock {
+                language: Some("x\"><script>".into()),
+                code: "a && <b>".into(),
+            },
         ];
         assert_eq!(
-            render_html(tokens),
-            "<ul><li>Hello</li><li>Hi <i>there</i></li><ul><li>Hello</li></ul><li>Hola</li></ul>"
+            render(blocks),
+            [
+                "<p>1 &lt; 2 &amp; 3 &gt; 2 &amp;copy;<code>&lt;div&gt;</code></p>",
+                "<pre><code class=\"language-x&quot;&gt;&lt;script&gt;\">a &amp;&amp; &lt;b&gt;</code></pre>",
+            ]
+            .join("")
+        );
+    }
+
+    fn raw_html_document() -> Document {
+        Document {
+            blocks: vec![
+                Block::Html("<div onclick=\"x()\">\n<script>alert(1)</script>\n</div>".into()),
+                paragraph(vec![
+                    text("Press "),
+                    Inline::Html("<kbd>".into()),
+                    text("q"),
+                    Inline::Html("</kbd>".into()),
+                    text(" "),
+                    Inline::Link {
+                        url: "javascript:alert(1)".into(),
+                        title: None,
+                        content: vec![text("now")],
+                    },
+                ]),
+            ],
+        }
+    }
+
+    #[test]
+    fn raw_html_sanitize() {
+        let options = Options {
+            raw_html: RawHtml::Sanitize,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_html(&raw_html_document(), &options),
+            [
+                "<div>\n&lt;script&gt;alert(1)&lt;/script&gt;\n</div>\n",
+                "<p>Press <kbd>q</kbd> <a>now</a></p>",
+            ]
+            .join("")
+        );
+    }
+
+    #[test]
+    fn raw_html_escape() {
+        let options = Options {
+            raw_html: RawHtml::Escape,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_html(&raw_html_document(), &options),
+            [
+                "<p>&lt;div onclick=\"x()\"&gt;\n&lt;script&gt;alert(1)&lt;/script&gt;\n&lt;/div&gt;</p>",
+                "<p>Press &lt;kbd&gt;q&lt;/kbd&gt; <a>now</a></p>",
+            ]
+            .join("")
+        );
+    }
+
+    #[test]
+    fn raw_html_pass_through() {
+        let options = Options {
+            raw_html: RawHtml::PassThrough,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_html(&raw_html_document(), &options),
+            [
+                "<div onclick=\"x()\">\n<script>alert(1)</script>\n</div>\n",
+                "<p>Press <kbd>q</kbd> <a href=\"javascript:alert(1)\">now</a></p>",
+            ]
+            .join("")
         );
     }
 }
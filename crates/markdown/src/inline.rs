@@ -0,0 +1,990 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::Inline,
+    entities::decode_entity,
+    sanitize::{html_length, Unclosed},
+    Extensions,
+};
+
+/// Link reference definitions by normalized label: `[label]: url "title"`.
+pub type Definitions = HashMap<String, (String, Option<String>)>;
+
+/// Parses the raw text of a paragraph, heading or table cell into inlines.
+pub fn parse_inlines(text: &str, definitions: &Definitions, extensions: Extensions) -> Vec<Inline> {
+    let mut parser = InlineParser {
+        extensions,
+        text,
+        pos: 0,
+        items: Vec::new(),
+        brackets: Vec::new(),
+        definitions,
+        unclosed: Unclosed::default(),
+    };
+    parser.parse();
+    finish(parser.items)
+}
+
+enum Item {
+    Inline(Inline),
+    Delimiter(Delimiter),
+    /// The literal `[` or `![` of a bracket, replaced if it becomes a link.
+    Bracket {
+        image: bool,
+    },
+}
+
+/// A run of `*` or `_` that may open or close emphasis.
+struct Delimiter {
+    ch: char,
+    count: usize,
+    original_count: usize,
+    can_open: bool,
+    can_close: bool,
+}
+
+/// An opening `[` or `![` waiting for its `]`.
+struct Bracket {
+    index: usize,
+    source: usize,
+    image: bool,
+    active: bool,
+    bracket_after: bool,
+}
+
+struct InlineParser<'a> {
+    extensions: Extensions,
+    text: &'a str,
+    pos: usize,
+    items: Vec<Item>,
+    brackets: Vec<Bracket>,
+    definitions: &'a Definitions,
+    unclosed: Unclosed,
+}
+
+impl InlineParser<'_> {
+    fn parse(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                '\n' => self.parse_newline(),
+                '\\' => self.parse_backslash(),
+                '`' => self.parse_code_span(),
+                '*' | '_' => self.parse_delimiter_run(c),
+                '[' => self.parse_open_bracket(),
+                '!' if self.text[self.pos + 1..].starts_with('[') => {
+                    self.push_bracket(true);
+                    self.pos += 2;
+                }
+                ']' => self.parse_close_bracket(),
+                '<' => self.parse_angle_bracket(),
+                '&' => self.parse_entity(),
+                _ => self.parse_text(),
+            }
+        }
+
+        process_emphasis(&mut self.items);
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(Item::Inline(Inline::Text(last))) = self.items.last_mut() {
+            last.push_str(text);
+        } else {
+            self.items
+                .push(Item::Inline(Inline::Text(text.to_string())));
+        }
+    }
+
+    fn parse_text(&mut self) {
+        let rest = &self.text[self.pos..];
+        let first = rest.chars().next().map_or(0, char::len_utf8);
+        let end = rest[first..]
+            .find(['\n', '\\', '`', '*', '_', '[', ']', '!', '<', '&'])
+            .map_or(rest.len(), |end| end + first);
+        self.push_text(&rest[..end]);
+        self.pos += end;
+    }
+
+    /// Turns a newline into a soft break, or a hard break when the line ends
+    /// with two or more spaces.
+    fn parse_newline(&mut self) {
+        let mut hard = false;
+        if let Some(Item::Inline(Inline::Text(last))) = self.items.last_mut() {
+            let trimmed = last.trim_end_matches(' ').len();
+            hard = last.len() - trimmed >= 2;
+            last.truncate(trimmed);
+            if last.is_empty() {
+                self.items.pop();
+            }
+        }
+
+        let line_break = if hard {
+            Inline::HardBreak
+        } else {
+            Inline::SoftBreak
+        };
+        self.items.push(Item::Inline(line_break));
+        self.pos += 1;
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_backslash(&mut self) {
+        self.pos += 1;
+        match self.peek() {
+            Some('\n') => {
+                self.items.push(Item::Inline(Inline::HardBreak));
+                self.pos += 1;
+                while matches!(self.peek(), Some(' ') | Some('\t')) {
+                    self.pos += 1;
+                }
+            }
+            Some(c) if c.is_ascii_punctuation() => {
+                self.push_text(&c.to_string());
+                self.pos += 1;
+            }
+            _ => self.push_text("\\"),
+        }
+    }
+
+    fn parse_code_span(&mut self) {
+        let start = self.pos;
+        let ticks = count_run(&self.text[start..], '`');
+        let after = start + ticks;
+
+        let mut search = after;
+        while let Some(offset) = self.text[search..].find('`') {
+            let run_start = search + offset;
+            let run = count_run(&self.text[run_start..], '`');
+            if run == ticks {
+                let code = self.text[after..run_start].replace('\n', " ");
+                let code = match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+                    Some(stripped) if !code.bytes().all(|c| c == b' ') => stripped.to_string(),
+                    _ => code,
+                };
+                self.items.push(Item::Inline(Inline::Code(code)));
+                self.pos = run_start + run;
+                return;
+            }
+            search = run_start + run;
+        }
+
+        self.push_text(&self.text[start..after]);
+        self.pos = after;
+    }
+
+    fn parse_delimiter_run(&mut self, ch: char) {
+        let count = count_run(&self.text[self.pos..], ch);
+        let before = self.text[..self.pos].chars().next_back().unwrap_or('\n');
+        let after = self.text[self.pos + count..].chars().next().unwrap_or('\n');
+
+        let left_flanking = !after.is_whitespace()
+            && (!is_punctuation(after) || before.is_whitespace() || is_punctuation(before));
+        let right_flanking = !before.is_whitespace()
+            && (!is_punctuation(before) || after.is_whitespace() || is_punctuation(after));
+
+        let (can_open, can_close) = if ch == '*' {
+            (left_flanking, right_flanking)
+        } else {
+            (
+                left_flanking && (!right_flanking || is_punctuation(before)),
+                right_flanking && (!left_flanking || is_punctuation(after)),
+            )
+        };
+
+        self.items.push(Item::Delimiter(Delimiter {
+            ch,
+            count,
+            original_count: count,
+            can_open,
+            can_close,
+        }));
+        self.pos += count;
+    }
+
+    fn parse_open_bracket(&mut self) {
+        if self.extensions.wiki_links {
+            if let Some((wiki_link, length)) = parse_wiki_link(&self.text[self.pos..]) {
+                self.items.push(Item::Inline(wiki_link));
+                self.pos += length;
+                return;
+            }
+        }
+
+        self.push_bracket(false);
+        self.pos += 1;
+    }
+
+    fn push_bracket(&mut self, image: bool) {
+        if let Some(bracket) = self.brackets.last_mut() {
+            bracket.bracket_after = true;
+        }
+        self.brackets.push(Bracket {
+            index: self.items.len(),
+            source: self.pos + if image { 2 } else { 1 },
+            image,
+            active: true,
+            bracket_after: false,
+        });
+        self.items.push(Item::Bracket { image });
+    }
+
+    fn parse_close_bracket(&mut self) {
+        let start = self.pos;
+        self.pos += 1;
+
+        let opener = match self.brackets.last() {
+            Some(opener) => opener,
+            None => {
+                self.push_text("]");
+                return;
+            }
+        };
+
+        if !opener.active {
+            self.brackets.pop();
+            self.push_text("]");
+            return;
+        }
+
+        let link = self
+            .parse_inline_link()
+            .or_else(|| self.parse_reference_link(start));
+
+        let (url, title) = match link {
+            Some(link) => link,
+            None => {
+                self.brackets.pop();
+                self.push_text("]");
+                return;
+            }
+        };
+
+        let opener = match self.brackets.pop() {
+            Some(opener) => opener,
+            None => return,
+        };
+        let mut content: Vec<Item> = self.items.drain(opener.index + 1..).collect();
+        self.items.truncate(opener.index);
+        process_emphasis(&mut content);
+        let content = finish(content);
+
+        if opener.image {
+            self.items.push(Item::Inline(Inline::Image {
+                url,
+                title,
+                alt: content,
+            }));
+        } else {
+            self.items.push(Item::Inline(Inline::Link {
+                url,
+                title,
+                content,
+            }));
+            // Links can't contain other links. Brackets before an inactive
+            // one are already inactive.
+            for bracket in self.brackets.iter_mut().rev() {
+                if !bracket.image {
+                    if !bracket.active {
+                        break;
+                    }
+                    bracket.active = false;
+                }
+            }
+        }
+    }
+
+    /// Parses `(url "title")` right after a `]`.
+    fn parse_inline_link(&mut self) -> Option<(String, Option<String>)> {
+        let text = self.text;
+        if !text[self.pos..].starts_with('(') {
+            return None;
+        }
+
+        let mut pos = skip_spaces_and_newline(text, self.pos + 1);
+        let (url, after_url) = match parse_link_destination(text, pos) {
+            Some(destination) => destination,
+            None if text[pos..].starts_with(')') => (String::new(), pos),
+            None => return None,
+        };
+        pos = after_url;
+
+        let before_title = pos;
+        pos = skip_spaces_and_newline(text, pos);
+        let mut title = None;
+        if pos > before_title {
+            if let Some((parsed, after_title)) = parse_link_title(text, pos) {
+                title = Some(parsed);
+                pos = skip_spaces_and_newline(text, after_title);
+            }
+        }
+
+        if !text[pos..].starts_with(')') {
+            return None;
+        }
+        self.pos = pos + 1;
+        Some((url, title))
+    }
+
+    /// Parses `[label]`, `[]` or nothing after the `]` at `close`, and looks
+    /// the label up in the link reference definitions.
+    fn parse_reference_link(&mut self, close: usize) -> Option<(String, Option<String>)> {
+        let opener = self.brackets.last()?;
+        let after_close = close + 1;
+        // Labels are at most 999 characters, which also keeps long runs of
+        // brackets from being copied over and over.
+        let fits = close - opener.source <= 999;
+
+        let (label, end) = match parse_link_label(self.text, after_close) {
+            Some((label, end)) if !label.is_empty() => (label, end),
+            Some((_, end)) if !opener.bracket_after && fits => {
+                (self.text[opener.source..close].to_string(), end)
+            }
+            None if !opener.bracket_after && fits => {
+                (self.text[opener.source..close].to_string(), after_close)
+            }
+            _ => return None,
+        };
+
+        let (url, title) = self.definitions.get(&normalize_label(&label))?;
+        self.pos = end;
+        Some((url.clone(), title.clone()))
+    }
+
+    /// Parses an autolink such as `<https://example.com>`, raw HTML, or a
+    /// literal `<`.
+    fn parse_angle_bracket(&mut self) {
+        let rest = &self.text[self.pos..];
+
+        // Autolinks can't have spaces or `<` in them, so the search for their
+        // end stops there instead of going through the rest of the text.
+        let end = rest[1..]
+            .find(|c: char| c == '>' || c == '<' || c <= ' ')
+            .map(|end| end + 1);
+        if let Some(end) = end.filter(|&end| rest[end..].starts_with('>')) {
+            let inner = &rest[1..end];
+            let url = if is_uri(inner) {
+                Some(inner.to_string())
+            } else if is_email(inner) {
+                Some(format!("mailto:{inner}"))
+            } else {
+                None
+            };
+
+            if let Some(url) = url {
+                self.items.push(Item::Inline(Inline::Link {
+                    url,
+                    title: None,
+                    content: vec![Inline::Text(inner.to_string())],
+                }));
+                self.pos += end + 1;
+                return;
+            }
+        }
+
+        match html_length(rest.as_bytes(), &mut self.unclosed) {
+            Some(length) => {
+                self.items
+                    .push(Item::Inline(Inline::Html(rest[..length].to_string())));
+                self.pos += length;
+            }
+            None => {
+                self.push_text("<");
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn parse_entity(&mut self) {
+        match decode_entity(&self.text[self.pos..]) {
+            Some((decoded, length)) => {
+                self.push_text(&decoded);
+                self.pos += length;
+            }
+            None => {
+                self.push_text("&");
+                self.pos += 1;
+            }
+        }
+    }
+}
+
+/// Marks the ends of the linked list in [`process_emphasis`].
+const NONE: usize = usize::MAX;
+
+/// Matches emphasis openers and closers, following the CommonMark delimiter
+/// algorithm, and wraps what's between them.
+fn process_emphasis(items: &mut Vec<Item>) {
+    // The items as a linked list over slots in their original order, so
+    // wrapping emphasis moves each item once instead of shifting the rest.
+    let len = items.len();
+    let mut slots: Vec<Option<Item>> = items.drain(..).map(Some).collect();
+    let mut next: Vec<usize> = (1..=len).map(|i| if i < len { i } else { NONE }).collect();
+    let mut prev: Vec<usize> = (0..len).map(|i| i.checked_sub(1).unwrap_or(NONE)).collect();
+    // The lowest slot worth searching for an opener, by the character of the
+    // closer, whether it can open and its original length modulo 3, so
+    // hopeless searches aren't repeated and long runs of delimiters stay
+    // linear.
+    let mut openers_bottom = [[[0usize; 3]; 2]; 2];
+
+    let mut closer = if len > 0 { 0 } else { NONE };
+    while closer != NONE {
+        let (ch, closer_count, closer_original, closer_can_open) = match &slots[closer] {
+            Some(Item::Delimiter(d)) if d.can_close && d.count > 0 => {
+                (d.ch, d.count, d.original_count, d.can_open)
+            }
+            _ => {
+                closer = next[closer];
+                continue;
+            }
+        };
+        let bottom = &mut openers_bottom[usize::from(ch == '_')][usize::from(closer_can_open)]
+            [closer_original % 3];
+
+        let mut opener = prev[closer];
+        while opener != NONE && opener >= *bottom {
+            if let Some(Item::Delimiter(o)) = &slots[opener] {
+                // The "rule of three" keeps `*foo**bar*` from matching oddly
+                let odd_match = (closer_can_open || o.can_close)
+                    && (o.original_count + closer_original) % 3 == 0
+                    && !(o.original_count % 3 == 0 && closer_original % 3 == 0);
+                if o.ch == ch && o.can_open && o.count > 0 && !odd_match {
+                    break;
+                }
+            }
+            opener = prev[opener];
+        }
+        if opener == NONE || opener < *bottom {
+            *bottom = closer;
+            closer = next[closer];
+            continue;
+        }
+
+        let opener_count = match &slots[opener] {
+            Some(Item::Delimiter(o)) => o.count,
+            _ => 0,
+        };
+        let used = if opener_count >= 2 && closer_count >= 2 {
+            2
+        } else {
+            1
+        };
+
+        if let Some(Item::Delimiter(o)) = &mut slots[opener] {
+            o.count -= used;
+        }
+        if let Some(Item::Delimiter(c)) = &mut slots[closer] {
+            c.count -= used;
+        }
+
+        // The node takes the slot of the first item it wraps.
+        let first = next[opener];
+        let mut content = Vec::new();
+        let mut i = first;
+        while i != closer {
+            content.extend(slots[i].take());
+            i = next[i];
+        }
+        if first != closer {
+            let content = finish(content);
+            let node = if used == 2 {
+                Inline::Strong(content)
+            } else {
+                Inline::Emphasis(content)
+            };
+            slots[first] = Some(Item::Inline(node));
+            next[first] = closer;
+            prev[closer] = first;
+        }
+
+        if opener_count == used {
+            unlink(opener, &mut slots, &mut next, &mut prev);
+        }
+        if closer_count == used {
+            let after = next[closer];
+            unlink(closer, &mut slots, &mut next, &mut prev);
+            closer = after;
+        }
+    }
+
+    items.extend(slots.into_iter().flatten());
+}
+
+fn unlink(i: usize, slots: &mut [Option<Item>], next: &mut [usize], prev: &mut [usize]) {
+    if prev[i] != NONE {
+        next[prev[i]] = next[i];
+    }
+    if next[i] != NONE {
+        prev[next[i]] = prev[i];
+    }
+    slots[i] = None;
+}
+
+/// Turns leftover delimiters into text and merges adjacent text.
+fn finish(items: Vec<Item>) -> Vec<Inline> {
+    let mut inlines: Vec<Inline> = Vec::new();
+    for item in items {
+        let inline = match item {
+            Item::Inline(inline) => inline,
+            Item::Delimiter(d) => Inline::Text(d.ch.to_string().repeat(d.count)),
+            Item::Bracket { image: true } => Inline::Text("![".to_string()),
+            Item::Bracket { image: false } => Inline::Text("[".to_string()),
+        };
+
+        match (inlines.last_mut(), inline) {
+            (_, Inline::Text(text)) if text.is_empty() => (),
+            (Some(Inline::Text(last)), Inline::Text(text)) => last.push_str(&text),
+            (_, inline) => inlines.push(inline),
+        }
+    }
+    inlines
+}
+
+fn count_run(text: &str, ch: char) -> usize {
+    text.chars().take_while(|&c| c == ch).count()
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || (!c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace())
+}
+
+/// Parses a `[[Page#Heading|label]]` link. Wiki links cannot span lines.
+fn parse_wiki_link(text: &str) -> Option<(Inline, usize)> {
+    let rest = text.strip_prefix("[[")?;
+    // The content can't have brackets or line breaks, so the first one ends
+    // it, which keeps a line of `[[` from being searched over and over.
+    let end = rest.find(['[', ']', '\n'])?;
+    let content = &rest[..end];
+    if !rest[end..].starts_with("]]") || content.trim().is_empty() {
+        return None;
+    }
+
+    let (target, label) = match content.split_once('|') {
+        Some((target, label)) => (target.trim(), Some(label.trim().to_string())),
+        None => (content.trim(), None),
+    };
+    let wiki_link = Inline::WikiLink {
+        target: target.to_string(),
+        label,
+    };
+    Some((wiki_link, end + 4))
+}
+
+fn is_uri(text: &str) -> bool {
+    let (scheme, rest) = match text.split_once(':') {
+        Some(split) => split,
+        None => return false,
+    };
+    let mut chars = scheme.chars();
+    (2..=32).contains(&scheme.len())
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+        && !rest
+            .chars()
+            .any(|c| c == '<' || c == '>' || c <= ' ' || c == '\u{7F}')
+}
+
+fn is_email(text: &str) -> bool {
+    let (local, domain) = match text.split_once('@') {
+        Some(split) => split,
+        None => return false,
+    };
+    let local_ok = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c));
+    let domain_ok = domain.split('.').all(|label| {
+        (1..=63).contains(&label.len())
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    });
+    local_ok && domain_ok
+}
+
+/// Skips spaces and tabs, and at most one newline.
+pub fn skip_spaces_and_newline(text: &str, mut pos: usize) -> usize {
+    let bytes = text.as_bytes();
+    while matches!(bytes.get(pos), Some(b' ') | Some(b'\t')) {
+        pos += 1;
+    }
+    if bytes.get(pos) == Some(&b'\n') {
+        pos += 1;
+        while matches!(bytes.get(pos), Some(b' ') | Some(b'\t')) {
+            pos += 1;
+        }
+    }
+    pos
+}
+
+/// Parses a `[label]` starting at `pos`, returning the raw label and the
+/// position after the `]`.
+pub fn parse_link_label(text: &str, pos: usize) -> Option<(String, usize)> {
+    let bytes = text.as_bytes();
+    if bytes.get(pos) != Some(&b'[') {
+        return None;
+    }
+
+    let mut i = pos + 1;
+    while i < bytes.len() && i - pos <= 1000 {
+        match bytes[i] {
+            b'\\' if bytes.get(i + 1).is_some_and(u8::is_ascii_punctuation) => i += 2,
+            b'[' => return None,
+            b']' => return Some((text[pos + 1..i].to_string(), i + 1)),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// How deep parentheses in a link destination may nest. The spec allows a
+/// limit, and without one every `](` of `[a](b[a](b...` would be searched to
+/// the end of the text.
+const MAX_PAREN_DEPTH: usize = 32;
+
+/// Parses a link destination, either `<...>` or a run of non-whitespace
+/// characters with balanced parentheses, with escapes and entities decoded.
+pub fn parse_link_destination(text: &str, pos: usize) -> Option<(String, usize)> {
+    let bytes = text.as_bytes();
+
+    if bytes.get(pos) == Some(&b'<') {
+        let mut i = pos + 1;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' if bytes.get(i + 1).is_some_and(u8::is_ascii_punctuation) => i += 2,
+                b'>' => return Some((unescape(&text[pos + 1..i]), i + 1)),
+                b'<' | b'\n' => return None,
+                _ => i += 1,
+            }
+        }
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut i = pos;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if bytes.get(i + 1).is_some_and(u8::is_ascii_punctuation) => {
+                i += 2;
+                continue;
+            }
+            b'(' if depth == MAX_PAREN_DEPTH => return None,
+            b'(' => depth += 1,
+            b')' if depth == 0 => break,
+            b')' => depth -= 1,
+            c if c <= b' ' || c == 0x7F => break,
+            _ => (),
+        }
+        i += 1;
+    }
+
+    if i == pos || depth != 0 {
+        return None;
+    }
+    Some((unescape(&text[pos..i]), i))
+}
+
+/// Parses a `"title"`, `'title'` or `(title)`.
+pub fn parse_link_title(text: &str, pos: usize) -> Option<(String, usize)> {
+    let bytes = text.as_bytes();
+    let close = match bytes.get(pos)? {
+        b'"' => b'"',
+        b'\'' => b'\'',
+        b'(' => b')',
+        _ => return None,
+    };
+
+    let mut i = pos + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if bytes.get(i + 1).is_some_and(u8::is_ascii_punctuation) => i += 2,
+            c if c == close => return Some((unescape(&text[pos + 1..i]), i + 1)),
+            b'(' if close == b')' => return None,
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Decodes backslash escapes and entities, as used in link destinations,
+/// titles and code block info strings.
+pub fn unescape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some('\\') if chars.next().is_some_and(|c| c.is_ascii_punctuation()) => {
+                output.push_str(&rest[1..2]);
+                i += 2;
+            }
+            Some('&') => match decode_entity(rest) {
+                Some((decoded, length)) => {
+                    output.push_str(&decoded);
+                    i += length;
+                }
+                None => {
+                    output.push('&');
+                    i += 1;
+                }
+            },
+            Some(c) => {
+                output.push(c);
+                i += c.len_utf8();
+            }
+            None => break,
+        }
+    }
+    output
+}
+
+/// Reference labels are matched case-insensitively with whitespace collapsed.
+pub fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<Inline> {
+        parse_inlines(text, &Definitions::new(), Extensions::default())
+    }
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.into())
+    }
+
+    #[test]
+    fn bold_and_italic() {
+        assert_eq!(
+            parse("hi **hi** *hi* ***hi***"),
+            vec![
+                text("hi "),
+                Inline::Strong(vec![text("hi")]),
+                text(" "),
+                Inline::Emphasis(vec![text("hi")]),
+                text(" "),
+                Inline::Emphasis(vec![Inline::Strong(vec![text("hi")])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn underscores() {
+        assert_eq!(
+            parse("_a_ snake_case __b__"),
+            vec![
+                Inline::Emphasis(vec![text("a")]),
+                text(" snake_case "),
+                Inline::Strong(vec![text("b")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_delimiters() {
+        assert_eq!(parse("**a *b"), vec![text("**a *b")]);
+        assert_eq!(
+            parse("*a **b*"),
+            vec![text("*a *"), Inline::Emphasis(vec![text("b")])]
+        );
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(
+            parse("Example: `code` `` a ` b `` `x"),
+            vec![
+                text("Example: "),
+                Inline::Code("code".into()),
+                text(" "),
+                Inline::Code("a ` b".into()),
+                text(" `x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(
+            parse("a\n  b  \nc\\\nd"),
+            vec![
+                text("a"),
+                Inline::SoftBreak,
+                text("b"),
+                Inline::HardBreak,
+                text("c"),
+                Inline::HardBreak,
+                text("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_and_entities() {
+        assert_eq!(
+            parse("⛺ \\*not\\* &amp; &copy; &nope; \\a"),
+            vec![text("⛺ *not* & \u{A9} &nope; \\a")]
+        );
+    }
+
+    #[test]
+    fn inline_links() {
+        assert_eq!(
+            parse("See [the **docs**](https://example.com \"Docs\") or [this](<a b>)."),
+            vec![
+                text("See "),
+                Inline::Link {
+                    url: "https://example.com".into(),
+                    title: Some("Docs".into()),
+                    content: vec![text("the "), Inline::Strong(vec![text("docs")])],
+                },
+                text(" or "),
+                Inline::Link {
+                    url: "a b".into(),
+                    title: None,
+                    content: vec![text("this")],
+                },
+                text("."),
+            ]
+        );
+    }
+
+    #[test]
+    fn reference_links() {
+        let mut definitions = Definitions::new();
+        definitions.insert("REF".into(), ("/ref".into(), None));
+        definitions.insert("B".into(), ("/b".into(), Some("B".into())));
+        let link = |url: &str, title: Option<&str>, content: &str| Inline::Link {
+            url: url.into(),
+            title: title.map(Into::into),
+            content: vec![text(content)],
+        };
+        assert_eq!(
+            parse_inlines(
+                "[a][Ref] [b][] [B] [c][missing] [ref]",
+                &definitions,
+                Extensions::default()
+            ),
+            vec![
+                link("/ref", None, "a"),
+                text(" "),
+                link("/b", Some("B"), "b"),
+                text(" "),
+                link("/b", Some("B"), "B"),
+                text(" [c][missing] "),
+                link("/ref", None, "ref"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_links() {
+        assert_eq!(
+            parse("[a [b](c) d](e)"),
+            vec![
+                text("[a "),
+                Inline::Link {
+                    url: "c".into(),
+                    title: None,
+                    content: vec![text("b")],
+                },
+                text(" d](e)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn images() {
+        assert_eq!(
+            parse("Look! ![A *diagram*](img/a.png \"Arch\") !"),
+            vec![
+                text("Look! "),
+                Inline::Image {
+                    url: "img/a.png".into(),
+                    title: Some("Arch".into()),
+                    alt: vec![text("A "), Inline::Emphasis(vec![text("diagram")])],
+                },
+                text(" !"),
+            ]
+        );
+    }
+
+    #[test]
+    fn wiki_links() {
+        assert_eq!(
+            parse("[[Page Name]], [[Guides/Setup#Install|install it]] [[x]"),
+            vec![
+                Inline::WikiLink {
+                    target: "Page Name".into(),
+                    label: None,
+                },
+                text(", "),
+                Inline::WikiLink {
+                    target: "Guides/Setup#Install".into(),
+                    label: Some("install it".into()),
+                },
+                text(" [[x]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn autolinks_and_html() {
+        assert_eq!(
+            parse("<https://example.com> <me@example.com> <kbd>q</kbd> a < b"),
+            vec![
+                Inline::Link {
+                    url: "https://example.com".into(),
+                    title: None,
+                    content: vec![text("https://example.com")],
+                },
+                text(" "),
+                Inline::Link {
+                    url: "mailto:me@example.com".into(),
+                    title: None,
+                    content: vec![text("me@example.com")],
+                },
+                text(" "),
+                Inline::Html("<kbd>".into()),
+                text("q"),
+                Inline::Html("</kbd>".into()),
+                text(" a < b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pathological_input() {
+        let inputs = [
+            "*_".repeat(20000),
+            "*a **b ".repeat(10000),
+            "[".repeat(50000),
+            "[[".repeat(25000),
+            format!("{}a{}", "[".repeat(20000), "](x)".repeat(20000)),
+            "[a](".repeat(25000),
+            "[a](b".repeat(20000),
+            "<a ".repeat(40000),
+            "<http://".repeat(20000),
+            "<!--".repeat(25000),
+            "&#".repeat(50000),
+        ];
+        for input in inputs {
+            let start = std::time::Instant::now();
+            parse(&input);
+            let elapsed = start.elapsed();
+            assert!(elapsed.as_secs() < 2, "{:?}: {elapsed:?}", &input[..8]);
+        }
+    }
+}
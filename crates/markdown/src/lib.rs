@@ -1,25 +1,133 @@
-use html::render_html;
-use lexer::{Lexer, Token};
+use ast::Document;
 
+pub use front_matter::{parse_front_matter, strip_front_matter, FrontMatterError, Metadata};
+pub use html::{render_html, render_toc};
+pub use parser::{parse, parse_with_extensions};
+pub use sanitize::{escape_attribute, escape_html};
+pub use toc::{table_of_contents, TocEntry};
+pub use url::encode_path_segment;
+
+pub mod ast;
+mod entities;
+mod front_matter;
 mod html;
-mod lexer;
+mod inline;
+mod parser;
+mod sanitize;
+pub mod slug;
+mod toc;
+mod url;
 
-pub fn to_html(markdown: &str) -> String {
-    render_html(get_tokens(markdown))
+pub type WikiLinkResolver<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// What to do with raw HTML written in the markdown.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum RawHtml {
+    /// Write it to the output as is. Only for trusted content.
+    PassThrough,
+    /// Show it as text. Links and images with `javascript:` and similar URLs
+    /// lose them.
+    Escape,
+    /// Keep allow-listed tags and attributes, and show everything else as
+    /// text. URLs are checked as with `Escape`.
+    #[default]
+    Sanitize,
+}
+
+/// Syntax on top of CommonMark. Everything is enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub struct Extensions {
+    /// GitHub-style pipe tables.
+    pub tables: bool,
+    /// `> [!NOTE]` callouts.
+    pub admonitions: bool,
+    /// `[[Page]]` links.
+    pub wiki_links: bool,
+    /// `[TOC]` and `[[_TOC_]]` placeholders for the table of contents.
+    pub toc: bool,
+}
+
+impl Extensions {
+    /// The names [`Extensions::enable`] takes.
+    pub const NAMES: &'static [&'static str] = &["tables", "admonitions", "wiki_links", "toc"];
+
+    pub fn none() -> Self {
+        Extensions {
+            tables: false,
+            admonitions: false,
+            wiki_links: false,
+            toc: false,
+        }
+    }
+
+    /// Turns on an extension by name, returning `false` for unknown names.
+    pub fn enable(&mut self, name: &str) -> bool {
+        let enabled = match name {
+            "tables" => &mut self.tables,
+            "admonitions" => &mut self.admonitions,
+            "wiki_links" => &mut self.wiki_links,
+            "toc" => &mut self.toc,
+            _ => return false,
+        };
+        *enabled = true;
+        true
+    }
 }
 
-fn get_tokens(markdown: &str) -> Vec<Token> {
-    let mut lexer = Lexer::new(markdown.to_string());
-    let mut tokens = Vec::new();
-    loop {
-        let token = lexer.next_token();
-        let eof = token == Token::EndOfFile;
-        tokens.push(token);
-        if eof {
-            break;
+impl Default for Extensions {
+    fn default() -> Self {
+        Extensions {
+            tables: true,
+            admonitions: true,
+            wiki_links: true,
+            toc: true,
         }
     }
-    tokens
+}
+
+#[derive(Default)]
+pub struct Options<'a> {
+    /// Resolves the page of a `[[Page]]` link to a URL. Pages it returns
+    /// `None` for, or every page when it is not set, render as red links.
+    pub wiki_link_resolver: Option<WikiLinkResolver<'a>>,
+    pub raw_html: RawHtml,
+    /// Adds a `#` link to each heading that points at the heading itself, for
+    /// themes to show on hover.
+    pub heading_anchors: bool,
+    pub extensions: Extensions,
+}
+
+/// A rendered document together with its front matter and table of
+/// contents, for layouts to show around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendered {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+    pub metadata: Metadata,
+    /// The parsed document, for indexing its text.
+    pub document: Document,
+}
+
+pub fn to_html(markdown: &str) -> String {
+    to_html_with_options(markdown, &Options::default())
+}
+
+pub fn to_html_with_options(markdown: &str, options: &Options) -> String {
+    render_html(
+        &parse_with_extensions(markdown, options.extensions),
+        options,
+    )
+}
+
+pub fn render(markdown: &str, options: &Options) -> Result<Rendered, FrontMatterError> {
+    let metadata = parse_front_matter(markdown)?;
+    let document = parse_with_extensions(strip_front_matter(markdown), options.extensions);
+    Ok(Rendered {
+        html: render_html(&document, options),
+        toc: table_of_contents(&document),
+        metadata,
+        document,
+    })
 }
 
 #[cfg(test)]
@@ -55,20 +163,195 @@ no language
   - def
 - ghi
 ";
-        let expected = vec![
-            "<h1>Hello World</h1>",
-            "<h2>Hello World</h2>",
-            "<h3>Hello World</h3>",
-            "<h4>Hello World</h4>",
-            "<h5>Hello World</h5>",
-            "<h6>Hello World</h6>",
-            "<p><i>Hello</i> <b>World</b>!<b> Hola</b> <code>Mundo</code>!</p>",
+        let expected = [
+            "<h1 id=\"hello-world\">Hello World</h1>",
+            "<h2 id=\"hello-world-1\">Hello World</h2>",
+            "<h3 id=\"hello-world-2\">Hello World</h3>",
+            "<h4 id=\"hello-world-3\">Hello World</h4>",
+            "<h5 id=\"hello-world-4\">Hello World</h5>",
+            "<h6 id=\"hello-world-5\">Hello World</h6>",
+            "<p><em>Hello</em> <strong>World</strong>!\n<strong>Hola</strong> <code>Mundo</code>!</p>",
             "<p>Yes, this is a paragraph.</p>",
             "<pre><code class=\"language-rust\">fn main() {\n    println!(\"Hello World\");\n}\n</code></pre>",
             "<pre><code>no language\n</code></pre>",
-            "<ul><li>abc</li><ul><li>def</li></ul><li>ghi</li></ul>",
+            "<ul><li>abc<ul><li>def</li></ul></li><li>ghi</li></ul>",
         ]
         .join("");
         assert_eq!(to_html(markdown), expected);
     }
+
+    #[test]
+    fn links() {
+        let markdown = "
+See [the guide](guide.html \"Guide\"), [the **FAQ**][faq] and [home].
+
+[faq]: /faq.html
+[Home]: / 'Start here'
+";
+        let expected = [
+            "<p>See <a href=\"guide.html\" title=\"Guide\">the guide</a>, ",
+            "<a href=\"/faq.html\">the <strong>FAQ</strong></a> and ",
+            "<a href=\"/\" title=\"Start here\">home</a>.</p>",
+        ]
+        .join("");
+        assert_eq!(to_html(markdown), expected);
+    }
+
+    #[test]
+    fn block_quotes() {
+        let markdown = "
+> [!NOTE]
+> Restart the service:
+>
+> ```sh
+> systemctl restart wiki
+> ```
+
+> Outer
+> > Inner
+";
+        let expected = [
+            "<div class=\"admonition note\"><p class=\"admonition-title\">Note</p>",
+            "<p>Restart the service:</p>",
+            "<pre><code class=\"language-sh\">systemctl restart wiki\n</code></pre></div>",
+            "<blockquote><p>Outer</p><blockquote><p>Inner</p></blockquote></blockquote>",
+        ]
+        .join("");
+        assert_eq!(to_html(markdown), expected);
+    }
+
+    #[test]
+    fn tables() {
+        let markdown = "
+Options:
+
+| Option | Default | Description |
+|--------|:-------:|------------:|
+| `port` | 8080 | Port to *listen* on |
+| `root` | . | A \\| separated list |
+";
+        let expected = [
+            "<p>Options:</p>",
+            "<table><thead><tr><th>Option</th><th align=\"center\">Default</th>",
+            "<th align=\"right\">Description</th></tr></thead><tbody>",
+            "<tr><td><code>port</code></td><td align=\"center\">8080</td>",
+            "<td align=\"right\">Port to <em>listen</em> on</td></tr>",
+            "<tr><td><code>root</code></td><td align=\"center\">.</td>",
+            "<td align=\"right\">A | separated list</td></tr>",
+            "</tbody></table>",
+        ]
+        .join("");
+        assert_eq!(to_html(markdown), expected);
+    }
+
+    #[test]
+    fn toc() {
+        let markdown = "
+# Runbook
+[[_TOC_]]
+## Restarting
+### The *worker*
+## Rolling back
+";
+        let rendered = render(markdown, &Options::default()).unwrap();
+        let expected = [
+            "<h1 id=\"runbook\">Runbook</h1>",
+            "<nav class=\"toc\"><ul><li><a href=\"#runbook\">Runbook</a><ul>",
+            "<li><a href=\"#restarting\">Restarting</a><ul>",
+            "<li><a href=\"#the-worker\">The worker</a></li></ul></li>",
+            "<li><a href=\"#rolling-back\">Rolling back</a></li>",
+            "</ul></li></ul></nav>",
+            "<h2 id=\"restarting\">Restarting</h2>",
+            "<h3 id=\"the-worker\">The <em>worker</em></h3>",
+            "<h2 id=\"rolling-back\">Rolling back</h2>",
+        ]
+        .join("");
+        assert_eq!(rendered.html, expected);
+        let [runbook] = rendered.toc.as_slice() else {
+            panic!("expected one top-level entry: {:?}", rendered.toc);
+        };
+        let children: Vec<_> = runbook.children.iter().map(|entry| &entry.text).collect();
+        assert_eq!(children, ["Restarting", "Rolling back"]);
+        assert_eq!(runbook.children[0].children[0].id, "the-worker");
+    }
+
+    #[test]
+    fn front_matter() {
+        let markdown = "---
+title: Runbook
+tags:
+  - ops
+---
+Body
+";
+        let rendered = render(markdown, &Options::default()).unwrap();
+        assert_eq!(rendered.html, "<p>Body</p>");
+        assert_eq!(rendered.metadata.title.as_deref(), Some("Runbook"));
+        assert_eq!(rendered.metadata.tags, ["ops"]);
+        // Only pages have front matter, plain markdown is CommonMark.
+        assert!(to_html(markdown).starts_with("<hr><p>title: Runbook"));
+
+        let error = render(
+            "+++
+draft = 1
++++
+",
+            &Options::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2: `draft` should be `true` or `false`"
+        );
+
+        // Not front matter, just a thematic break and a setext heading.
+        let markdown = "---\n\nIntro\n\nHeading\n---\n";
+        let rendered = render(markdown, &Options::default()).unwrap();
+        assert_eq!(rendered.metadata, Metadata::default());
+        assert_eq!(
+            rendered.html,
+            "<hr><p>Intro</p><h2 id=\"heading\">Heading</h2>"
+        );
+        assert_eq!(to_html(markdown), rendered.html);
+    }
+
+    #[test]
+    fn extensions() {
+        let markdown = "[TOC]\n\n# Title\n\n| a |\n| - |\n\n> [!NOTE]\n> Hi\n\n[[Page]]\n";
+        let render_with = |extensions| {
+            let options = Options {
+                extensions,
+                ..Default::default()
+            };
+            render(markdown, &options).unwrap().html
+        };
+
+        let html = render_with(Extensions::default());
+        assert!(html.contains("<nav class=\"toc\">"));
+        assert!(html.contains("<table>"));
+        assert!(html.contains("class=\"admonition note\""));
+        assert!(html.contains("wiki-link"));
+
+        let html = render_with(Extensions::none());
+        let options = Options {
+            extensions: Extensions::none(),
+            ..Default::default()
+        };
+        assert_eq!(to_html_with_options(markdown, &options), html);
+        assert!(html.starts_with("<p>[TOC]</p>"));
+        assert!(!html.contains("<table>"));
+        assert!(html.contains("<blockquote>"));
+        assert!(html.contains("<p>[[Page]]</p>"));
+
+        let mut extensions = Extensions::none();
+        assert!(extensions.enable("tables"));
+        assert!(!extensions.enable("footnotes"));
+        assert_eq!(
+            extensions,
+            Extensions {
+                tables: true,
+                ..Extensions::none()
+            }
+        );
+    }
 }
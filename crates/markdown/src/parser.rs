@@ -0,0 +1,1716 @@
+use crate::{
+    ast::{plain_text, Alignment, Block, Document, Inline, List, ListKind, Table},
+    inline::{
+        normalize_label, parse_inlines, parse_link_destination, parse_link_label, parse_link_title,
+        skip_spaces_and_newline, unescape, Definitions,
+    },
+    sanitize::{html_length, Unclosed},
+    slug::Slugger,
+    toc::{table_of_contents, TocEntry},
+    Extensions,
+};
+
+const CODE_INDENT: usize = 4;
+/// How deep block quotes and lists nest. Deeper ones are read as text, so
+/// matching a line against its containers stays cheap and rendering can't
+/// run out of stack.
+const MAX_NESTING: usize = 100;
+
+const ADMONITIONS: [&str; 5] = ["note", "tip", "important", "warning", "caution"];
+
+/// Tags that start an HTML block ending at the next blank line.
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "iframe",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "menu",
+    "menuitem",
+    "nav",
+    "noframes",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "param",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+];
+
+/// Tags whose HTML block runs until the matching closing tag, blank lines
+/// included.
+const RAW_TAGS: [&str; 4] = ["script", "pre", "style", "textarea"];
+
+/// Parses markdown into a document. Blocks are parsed line by line first,
+/// then the text of each leaf block is parsed into inlines, so links can use
+/// reference definitions from anywhere in the document. Front matter is
+/// markdown like everything else; strip it first with [`strip_front_matter`].
+///
+/// [`strip_front_matter`]: crate::strip_front_matter
+pub fn parse(markdown: &str) -> Document {
+    parse_with_extensions(markdown, Extensions::default())
+}
+
+pub fn parse_with_extensions(markdown: &str, extensions: Extensions) -> Document {
+    let markdown = markdown.replace('\0', "\u{FFFD}");
+    let mut parser = BlockParser::new(extensions);
+    for line in lines(&markdown) {
+        parser.incorporate_line(line);
+    }
+    while parser.stack.len() > 1 {
+        parser.finalize(parser.line_number);
+    }
+
+    let document = parser
+        .stack
+        .pop()
+        .unwrap_or_else(|| Node::new(Kind::Document, 0));
+    let mut blocks = convert_blocks(document.children, &parser.definitions, extensions);
+    assign_heading_ids(&mut blocks);
+    let mut document = Document { blocks };
+    let toc = table_of_contents(&document);
+    fill_tables_of_contents(&mut document.blocks, &toc);
+    document
+}
+
+/// Splits on `\n`, `\r\n` and `\r`, without an empty last line for input
+/// ending in a newline.
+fn lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => {
+                lines.push(&text[start..i]);
+                start = i + 1;
+            }
+            b'\r' => {
+                lines.push(&text[start..i]);
+                if bytes.get(i + 1) == Some(&b'\n') {
+                    i += 1;
+                }
+                start = i + 1;
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    lines
+}
+
+/// A block while it is being parsed. Leaf blocks collect their raw text in
+/// `content`.
+struct Node {
+    kind: Kind,
+    children: Vec<Node>,
+    content: String,
+    start_line: usize,
+    end_line: usize,
+}
+
+impl Node {
+    fn new(kind: Kind, start_line: usize) -> Self {
+        Node {
+            kind,
+            children: Vec::new(),
+            content: String::new(),
+            start_line,
+            end_line: start_line,
+        }
+    }
+}
+
+enum Kind {
+    Document,
+    BlockQuote,
+    List(Marker),
+    /// A list item, whose content is indented by `indent` columns.
+    Item {
+        indent: usize,
+    },
+    Paragraph,
+    Heading(u8),
+    FencedCode {
+        fence: char,
+        length: usize,
+        offset: usize,
+    },
+    IndentedCode,
+    /// An HTML block of one of the seven kinds from the CommonMark spec,
+    /// which differ in how they end.
+    Html(u8),
+    ThematicBreak,
+    Table {
+        alignments: Vec<Alignment>,
+        header: Vec<String>,
+    },
+}
+
+impl Kind {
+    fn accepts_lines(&self) -> bool {
+        matches!(
+            self,
+            Kind::Paragraph
+                | Kind::FencedCode { .. }
+                | Kind::IndentedCode
+                | Kind::Html(_)
+                | Kind::Table { .. }
+        )
+    }
+
+    fn can_contain(&self, child: &Kind) -> bool {
+        match self {
+            Kind::Document | Kind::BlockQuote | Kind::Item { .. } => {
+                !matches!(child, Kind::Item { .. })
+            }
+            Kind::List(_) => matches!(child, Kind::Item { .. }),
+            _ => false,
+        }
+    }
+}
+
+/// The bullet character or ordered delimiter of a list. Items with a
+/// different marker start a new list.
+#[derive(Clone, Copy)]
+struct Marker {
+    kind: ListKind,
+    delimiter: char,
+}
+
+impl Marker {
+    fn continues(self, other: Marker) -> bool {
+        self.delimiter == other.delimiter
+            && matches!(
+                (self.kind, other.kind),
+                (ListKind::Unordered, ListKind::Unordered)
+                    | (ListKind::Ordered(_), ListKind::Ordered(_))
+            )
+    }
+}
+
+enum Continuation {
+    Matched,
+    Failed,
+    /// The line closed a fenced code block and nothing else is left of it.
+    Done,
+}
+
+enum Start {
+    None,
+    Container,
+    Leaf,
+}
+
+struct BlockParser<'a> {
+    extensions: Extensions,
+    /// The open blocks, from the document down to the innermost one.
+    stack: Vec<Node>,
+    definitions: Definitions,
+    line: &'a str,
+    line_number: usize,
+    /// Where the line's leading spaces and tabs end, and whether there are
+    /// tabs among them.
+    leading_end: usize,
+    leading_tabs: bool,
+    offset: usize,
+    column: usize,
+    next_nonspace: usize,
+    next_nonspace_column: usize,
+    indent: usize,
+    indented: bool,
+    blank: bool,
+    partially_consumed_tab: bool,
+    all_closed: bool,
+    last_matched: usize,
+}
+
+impl<'a> BlockParser<'a> {
+    fn new(extensions: Extensions) -> Self {
+        BlockParser {
+            extensions,
+            stack: vec![Node::new(Kind::Document, 1)],
+            definitions: Definitions::new(),
+            line: "",
+            line_number: 0,
+            leading_end: 0,
+            leading_tabs: false,
+            offset: 0,
+            column: 0,
+            next_nonspace: 0,
+            next_nonspace_column: 0,
+            indent: 0,
+            indented: false,
+            blank: false,
+            partially_consumed_tab: false,
+            all_closed: true,
+            last_matched: 0,
+        }
+    }
+
+    fn tip(&self) -> &Node {
+        &self.stack[self.stack.len() - 1]
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.line[self.next_nonspace..]
+    }
+
+    fn incorporate_line(&mut self, line: &'a str) {
+        self.line = line;
+        self.line_number += 1;
+        self.leading_end = line.len() - line.trim_start_matches([' ', '\t']).len();
+        self.leading_tabs = line[..self.leading_end].contains('\t');
+        self.offset = 0;
+        self.column = 0;
+        self.blank = false;
+        self.partially_consumed_tab = false;
+
+        // Match the line against the open blocks, each of which may consume a
+        // prefix such as `>` or the indentation of a list item
+        let old_tip = self.stack.len() - 1;
+        let mut container = 0;
+        for i in 1..self.stack.len() {
+            self.find_next_nonspace();
+            match self.continues(i) {
+                Continuation::Matched => container = i,
+                Continuation::Failed => break,
+                Continuation::Done => return,
+            }
+        }
+        self.all_closed = container == old_tip;
+        self.last_matched = container;
+
+        // Then look for new blocks starting inside the last matched one
+        let kind = &self.stack[container].kind;
+        let mut matched_leaf = !matches!(kind, Kind::Paragraph) && kind.accepts_lines();
+        while !matched_leaf {
+            self.find_next_nonspace();
+            match self.try_starts(container) {
+                Start::Container => container = self.stack.len() - 1,
+                Start::Leaf => {
+                    container = self.stack.len() - 1;
+                    matched_leaf = true;
+                }
+                Start::None => {
+                    self.advance_next_nonspace();
+                    break;
+                }
+            }
+        }
+
+        // What's left of the line is text, possibly continuing a paragraph
+        // lazily without the prefixes of its containers
+        if !self.all_closed && !self.blank && matches!(self.tip().kind, Kind::Paragraph) {
+            self.add_line();
+            return;
+        }
+
+        self.close_unmatched_blocks();
+        if self.stack[container].kind.accepts_lines() {
+            self.add_line();
+            if let Kind::Html(kind @ 1..=5) = self.tip().kind {
+                if html_block_ends(kind, &self.line[self.offset..]) {
+                    self.finalize(self.line_number);
+                }
+            }
+        } else if self.offset < self.line.len() && !self.blank {
+            self.add_child(Kind::Paragraph);
+            self.advance_next_nonspace();
+            self.add_line();
+        }
+    }
+
+    fn continues(&mut self, index: usize) -> Continuation {
+        let rest = self.rest();
+        let matched = match self.stack[index].kind {
+            Kind::Document | Kind::List(_) => true,
+            Kind::BlockQuote => {
+                let quoted = !self.indented && rest.starts_with('>');
+                if quoted {
+                    self.advance_next_nonspace();
+                    self.advance_offset(1, false);
+                    if matches!(self.peek(), Some(b' ') | Some(b'\t')) {
+                        self.advance_offset(1, true);
+                    }
+                }
+                quoted
+            }
+            Kind::Item { indent } => {
+                if self.blank {
+                    // An item can start with at most one blank line
+                    let empty =
+                        self.stack[index].children.is_empty() && (index + 1 == self.stack.len());
+                    if !empty {
+                        self.advance_next_nonspace();
+                    }
+                    !empty
+                } else if self.indent >= indent {
+                    self.advance_offset(indent, true);
+                    true
+                } else {
+                    false
+                }
+            }
+            Kind::FencedCode {
+                fence,
+                length,
+                offset,
+            } => {
+                let closing = if self.indent <= 3 {
+                    closing_fence(rest, fence)
+                } else {
+                    None
+                };
+                if closing.is_some_and(|closing| closing >= length) {
+                    self.finalize(self.line_number);
+                    return Continuation::Done;
+                }
+
+                let mut skip = offset;
+                while skip > 0 && matches!(self.peek(), Some(b' ') | Some(b'\t')) {
+                    self.advance_offset(1, true);
+                    skip -= 1;
+                }
+                true
+            }
+            Kind::IndentedCode => {
+                if self.indent >= CODE_INDENT {
+                    self.advance_offset(CODE_INDENT, true);
+                    true
+                } else if self.blank {
+                    self.advance_next_nonspace();
+                    true
+                } else {
+                    false
+                }
+            }
+            Kind::Html(kind) => !(self.blank && (kind == 6 || kind == 7)),
+            Kind::Paragraph => !self.blank,
+            Kind::Table { .. } => !self.blank && (self.indented || !interrupts_table(rest)),
+            Kind::Heading(_) | Kind::ThematicBreak => false,
+        };
+
+        if matched {
+            Continuation::Matched
+        } else {
+            Continuation::Failed
+        }
+    }
+
+    /// Tries each kind of block start in turn.
+    fn try_starts(&mut self, container: usize) -> Start {
+        let starts = [
+            Self::start_block_quote,
+            Self::start_atx_heading,
+            Self::start_fenced_code,
+            Self::start_html_block,
+            Self::start_table,
+            Self::start_setext_heading,
+            Self::start_thematic_break,
+            Self::start_list_item,
+            Self::start_indented_code,
+        ];
+        for start in starts {
+            match start(self, container) {
+                Start::None => continue,
+                result => return result,
+            }
+        }
+        Start::None
+    }
+
+    fn start_block_quote(&mut self, _container: usize) -> Start {
+        if self.indented || self.stack.len() >= MAX_NESTING || !self.rest().starts_with('>') {
+            return Start::None;
+        }
+
+        self.advance_next_nonspace();
+        self.advance_offset(1, false);
+        if matches!(self.peek(), Some(b' ') | Some(b'\t')) {
+            self.advance_offset(1, true);
+        }
+        self.close_unmatched_blocks();
+        self.add_child(Kind::BlockQuote);
+        Start::Container
+    }
+
+    fn start_atx_heading(&mut self, _container: usize) -> Start {
+        let (level, length) = match atx_heading(self.rest()) {
+            Some(heading) if !self.indented => heading,
+            _ => return Start::None,
+        };
+
+        self.advance_next_nonspace();
+        self.advance_offset(length, false);
+        self.close_unmatched_blocks();
+        self.add_child(Kind::Heading(level));
+        let content = strip_closing_sequence(&self.line[self.offset..]).to_string();
+        if let Some(node) = self.stack.last_mut() {
+            node.content = content;
+        }
+        self.offset = self.line.len();
+        Start::Leaf
+    }
+
+    fn start_fenced_code(&mut self, _container: usize) -> Start {
+        let (fence, length) = match code_fence(self.rest()) {
+            Some(fence) if !self.indented => fence,
+            _ => return Start::None,
+        };
+
+        self.close_unmatched_blocks();
+        self.add_child(Kind::FencedCode {
+            fence,
+            length,
+            offset: self.indent,
+        });
+        self.advance_next_nonspace();
+        self.advance_offset(length, false);
+        Start::Leaf
+    }
+
+    fn start_html_block(&mut self, container: usize) -> Start {
+        if self.indented || !self.rest().starts_with('<') {
+            return Start::None;
+        }
+
+        let kind = match html_block_start(self.rest()) {
+            Some(kind) => kind,
+            None => return Start::None,
+        };
+        // Other tags can't interrupt a paragraph
+        let maybe_lazy =
+            !self.all_closed && !self.blank && matches!(self.tip().kind, Kind::Paragraph);
+        if kind == 7 && (matches!(self.stack[container].kind, Kind::Paragraph) || maybe_lazy) {
+            return Start::None;
+        }
+
+        self.close_unmatched_blocks();
+        // The indentation is part of the HTML
+        self.add_child(Kind::Html(kind));
+        Start::Leaf
+    }
+
+    /// Turns a one-line paragraph into the header of a table when the line
+    /// below it is a delimiter row with as many cells.
+    fn start_table(&mut self, container: usize) -> Start {
+        let paragraph = &self.stack[container];
+        if !self.extensions.tables || self.indented || !matches!(paragraph.kind, Kind::Paragraph) {
+            return Start::None;
+        }
+
+        let header_line = paragraph.content.trim_end_matches('\n');
+        if header_line.contains('\n') {
+            return Start::None;
+        }
+        let header = match split_table_row(header_line) {
+            Some(header) => header,
+            None => return Start::None,
+        };
+        let alignments = match split_table_row(self.rest()) {
+            Some(cells) => cells
+                .iter()
+                .map(|cell| parse_alignment(cell))
+                .collect::<Option<Vec<_>>>(),
+            None => None,
+        };
+        let alignments = match alignments {
+            Some(alignments) if alignments.len() == header.len() => alignments,
+            _ => return Start::None,
+        };
+
+        self.close_unmatched_blocks();
+        if let Some(node) = self.stack.last_mut() {
+            node.kind = Kind::Table { alignments, header };
+            node.content.clear();
+        }
+        self.offset = self.line.len();
+        Start::Leaf
+    }
+
+    fn start_setext_heading(&mut self, container: usize) -> Start {
+        let level = match setext_heading(self.rest()) {
+            Some(level) if !self.indented => level,
+            _ => return Start::None,
+        };
+        if !matches!(self.stack[container].kind, Kind::Paragraph) {
+            return Start::None;
+        }
+
+        self.close_unmatched_blocks();
+        let content = std::mem::take(&mut self.stack[container].content);
+        let content = self.extract_definitions(content);
+        if content.is_empty() {
+            self.stack[container].content = content;
+            return Start::None;
+        }
+
+        if let Some(node) = self.stack.last_mut() {
+            node.kind = Kind::Heading(level);
+            node.content = content;
+        }
+        self.offset = self.line.len();
+        Start::Leaf
+    }
+
+    fn start_thematic_break(&mut self, _container: usize) -> Start {
+        if self.indented || !is_thematic_break(self.rest()) {
+            return Start::None;
+        }
+
+        self.close_unmatched_blocks();
+        self.add_child(Kind::ThematicBreak);
+        self.offset = self.line.len();
+        Start::Leaf
+    }
+
+    fn start_list_item(&mut self, container: usize) -> Start {
+        let in_list = matches!(self.stack[container].kind, Kind::List(_));
+        if (self.indented && !in_list) || self.stack.len() >= MAX_NESTING {
+            return Start::None;
+        }
+        let (marker, indent) = match self.parse_list_marker(container) {
+            Some(item) => item,
+            None => return Start::None,
+        };
+
+        self.close_unmatched_blocks();
+        let continues_list = match self.tip().kind {
+            Kind::List(list) => list.continues(marker),
+            _ => false,
+        };
+        if !continues_list {
+            self.add_child(Kind::List(marker));
+        }
+        self.add_child(Kind::Item { indent });
+        Start::Container
+    }
+
+    fn start_indented_code(&mut self, _container: usize) -> Start {
+        if !self.indented || self.blank || matches!(self.tip().kind, Kind::Paragraph) {
+            return Start::None;
+        }
+
+        self.advance_offset(CODE_INDENT, true);
+        self.close_unmatched_blocks();
+        self.add_child(Kind::IndentedCode);
+        Start::Leaf
+    }
+
+    /// Parses a `-`, `*`, `+`, `1.` or `1)` list marker, returning it with
+    /// the indentation of the item's content.
+    fn parse_list_marker(&mut self, container: usize) -> Option<(Marker, usize)> {
+        if self.indent >= CODE_INDENT {
+            return None;
+        }
+
+        let rest = self.rest();
+        let interrupts_paragraph = matches!(self.stack[container].kind, Kind::Paragraph);
+        let (marker, length) = match rest.as_bytes().first()? {
+            c @ (b'-' | b'*' | b'+') => {
+                let marker = Marker {
+                    kind: ListKind::Unordered,
+                    delimiter: *c as char,
+                };
+                (marker, 1)
+            }
+            _ => {
+                let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+                let delimiter = rest.as_bytes().get(digits).copied();
+                if !(1..=9).contains(&digits) || !matches!(delimiter, Some(b'.') | Some(b')')) {
+                    return None;
+                }
+                let start = rest[..digits].parse().ok()?;
+                // Only lists starting at 1 can interrupt a paragraph
+                if interrupts_paragraph && start != 1 {
+                    return None;
+                }
+                let marker = Marker {
+                    kind: ListKind::Ordered(start),
+                    delimiter: delimiter? as char,
+                };
+                (marker, digits + 1)
+            }
+        };
+
+        let after = &rest[length..];
+        if !(after.is_empty() || after.starts_with([' ', '\t'])) {
+            return None;
+        }
+        if interrupts_paragraph && after.trim().is_empty() {
+            return None;
+        }
+
+        let marker_offset = self.indent;
+        self.advance_next_nonspace();
+        self.advance_offset(length, true);
+        let spaces_start_column = self.column;
+        let spaces_start_offset = self.offset;
+        loop {
+            self.advance_offset(1, true);
+            let next = self.peek();
+            if self.column - spaces_start_column >= 5 || !matches!(next, Some(b' ') | Some(b'\t')) {
+                break;
+            }
+        }
+
+        let blank_item = self.peek().is_none();
+        let spaces = self.column - spaces_start_column;
+        let padding = if !(1..5).contains(&spaces) || blank_item {
+            // Content indented by five or more spaces is an indented code
+            // block, so only the first space belongs to the marker
+            self.column = spaces_start_column;
+            self.offset = spaces_start_offset;
+            if matches!(self.peek(), Some(b' ') | Some(b'\t')) {
+                self.advance_offset(1, true);
+            }
+            length + 1
+        } else {
+            length + spaces
+        };
+
+        Some((marker, marker_offset + padding))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.line.as_bytes().get(self.offset).copied()
+    }
+
+    fn find_next_nonspace(&mut self) {
+        let bytes = self.line.as_bytes();
+        let mut i = self.offset;
+        let mut column = self.column;
+        // Deeply nested lines would otherwise count the same indentation once
+        // for every container
+        if !self.leading_tabs && i <= self.leading_end {
+            column += self.leading_end - i;
+            i = self.leading_end;
+        }
+        while let Some(&c) = bytes.get(i) {
+            match c {
+                b' ' => column += 1,
+                b'\t' => column += 4 - column % 4,
+                _ => break,
+            }
+            i += 1;
+        }
+
+        self.blank = i == bytes.len();
+        self.next_nonspace = i;
+        self.next_nonspace_column = column;
+        self.indent = column - self.column;
+        self.indented = self.indent >= CODE_INDENT;
+    }
+
+    fn advance_next_nonspace(&mut self) {
+        self.offset = self.next_nonspace;
+        self.column = self.next_nonspace_column;
+        self.partially_consumed_tab = false;
+    }
+
+    /// Advances by `count` characters, or by `count` columns when `columns`
+    /// is set, in which case a tab may be consumed only partially.
+    fn advance_offset(&mut self, mut count: usize, columns: bool) {
+        while count > 0 {
+            let c = match self.line[self.offset..].chars().next() {
+                Some(c) => c,
+                None => break,
+            };
+            if c == '\t' {
+                let to_tab = 4 - self.column % 4;
+                if columns {
+                    self.partially_consumed_tab = to_tab > count;
+                    let advance = to_tab.min(count);
+                    self.column += advance;
+                    if !self.partially_consumed_tab {
+                        self.offset += 1;
+                    }
+                    count -= advance;
+                } else {
+                    self.partially_consumed_tab = false;
+                    self.column += to_tab;
+                    self.offset += 1;
+                    count -= 1;
+                }
+            } else {
+                self.partially_consumed_tab = false;
+                self.offset += c.len_utf8();
+                self.column += 1;
+                count -= 1;
+            }
+        }
+    }
+
+    fn add_line(&mut self) {
+        let mut text = String::new();
+        if self.partially_consumed_tab {
+            // The rest of the tab is content, as spaces
+            self.offset += 1;
+            text.push_str(&" ".repeat(4 - self.column % 4));
+        }
+        text.push_str(&self.line[self.offset..]);
+        text.push('\n');
+
+        if let Some(tip) = self.stack.last_mut() {
+            tip.content.push_str(&text);
+        }
+    }
+
+    fn add_child(&mut self, kind: Kind) {
+        while !self.tip().kind.can_contain(&kind) {
+            self.finalize(self.line_number - 1);
+        }
+        self.stack.push(Node::new(kind, self.line_number));
+    }
+
+    fn close_unmatched_blocks(&mut self) {
+        if !self.all_closed {
+            while self.stack.len() - 1 > self.last_matched {
+                self.finalize(self.line_number - 1);
+            }
+            self.all_closed = true;
+        }
+    }
+
+    /// Closes the innermost open block, ending on `line_number`, and adds it
+    /// to its parent.
+    fn finalize(&mut self, line_number: usize) {
+        let mut node = match self.stack.pop() {
+            Some(node) => node,
+            None => return,
+        };
+        node.end_line = line_number;
+
+        match node.kind {
+            Kind::Paragraph => {
+                node.content = self.extract_definitions(std::mem::take(&mut node.content));
+                if node.content.is_empty() {
+                    return;
+                }
+            }
+            Kind::Item { .. } | Kind::List(_) => {
+                node.end_line = match node.children.last() {
+                    Some(child) => child.end_line,
+                    None => node.start_line,
+                };
+            }
+            _ => (),
+        }
+
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.stack.push(node),
+        }
+    }
+
+    /// Removes link reference definitions from the start of a paragraph,
+    /// returning what's left of it.
+    fn extract_definitions(&mut self, mut content: String) -> String {
+        while content.starts_with('[') {
+            match parse_reference(&content, &mut self.definitions) {
+                Some(length) => {
+                    content.drain(..length);
+                }
+                None => break,
+            }
+        }
+        if content.trim().is_empty() {
+            content.clear();
+        }
+        content
+    }
+}
+
+/// Parses a `[label]: url "title"` definition, returning its length.
+fn parse_reference(text: &str, definitions: &mut Definitions) -> Option<usize> {
+    let (label, after_label) = parse_link_label(text, 0)?;
+    if !text[after_label..].starts_with(':') {
+        return None;
+    }
+
+    let pos = skip_spaces_and_newline(text, after_label + 1);
+    let (url, after_url) = parse_link_destination(text, pos)?;
+
+    let mut title = None;
+    let mut end = None;
+    let pos = skip_spaces_and_newline(text, after_url);
+    if pos > after_url {
+        if let Some((parsed, after_title)) = parse_link_title(text, pos) {
+            title = Some(parsed);
+            end = line_end(text, after_title);
+        }
+    }
+    // A title followed by anything but spaces makes the definition end
+    // before the title, if it can
+    if end.is_none() {
+        title = None;
+        end = line_end(text, after_url);
+    }
+    let end = end?;
+
+    let label = normalize_label(&label);
+    if label.is_empty() {
+        return None;
+    }
+    definitions.entry(label).or_insert((url, title));
+    Some(end)
+}
+
+/// Returns the position after the end of the line, if there's nothing but
+/// spaces left on it.
+fn line_end(text: &str, mut pos: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    while matches!(bytes.get(pos), Some(b' ') | Some(b'\t')) {
+        pos += 1;
+    }
+    match bytes.get(pos) {
+        None => Some(pos),
+        Some(b'\n') => Some(pos + 1),
+        Some(_) => None,
+    }
+}
+
+fn convert_blocks(
+    nodes: Vec<Node>,
+    definitions: &Definitions,
+    extensions: Extensions,
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    for node in nodes {
+        if let Some(block) = convert_block(node, definitions, extensions) {
+            blocks.push(block);
+        }
+    }
+    blocks
+}
+
+fn convert_block(node: Node, definitions: &Definitions, extensions: Extensions) -> Option<Block> {
+    let block = match node.kind {
+        Kind::Document | Kind::Item { .. } => return None,
+        Kind::Paragraph if extensions.toc && is_toc_placeholder(node.content.trim()) => {
+            Block::TableOfContents(Vec::new())
+        }
+        Kind::Paragraph => {
+            Block::Paragraph(parse_inlines(node.content.trim(), definitions, extensions))
+        }
+        Kind::Heading(level) => {
+            let (text, id) = split_custom_id(&node.content);
+            Block::Heading {
+                level,
+                id,
+                content: parse_inlines(text, definitions, extensions),
+            }
+        }
+        Kind::FencedCode { .. } => {
+            let (info, code) = node.content.split_once('\n').unwrap_or((&node.content, ""));
+            let info = unescape(info.trim());
+            Block::CodeBlock {
+                language: info.split_whitespace().next().map(str::to_string),
+                code: code.to_string(),
+            }
+        }
+        Kind::IndentedCode => {
+            let mut lines: Vec<&str> = node.content.lines().collect();
+            while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                lines.pop();
+            }
+            Block::CodeBlock {
+                language: None,
+                code: lines.join("\n") + "\n",
+            }
+        }
+        Kind::Html(_) => Block::Html(node.content.trim_end_matches('\n').to_string()),
+        Kind::ThematicBreak => Block::ThematicBreak,
+        Kind::BlockQuote => convert_block_quote(node.children, definitions, extensions),
+        Kind::List(marker) => {
+            let tight = is_tight(&node.children);
+            let items = node
+                .children
+                .into_iter()
+                .map(|item| convert_blocks(item.children, definitions, extensions))
+                .collect();
+            Block::List(List {
+                kind: marker.kind,
+                tight,
+                items,
+            })
+        }
+        Kind::Table { alignments, header } => {
+            let parse_cells = |cells: Vec<String>| -> Vec<_> {
+                cells
+                    .iter()
+                    .map(|cell| parse_inlines(cell, definitions, extensions))
+                    .collect()
+            };
+            let rows = node
+                .content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    let mut cells =
+                        split_table_row(line).unwrap_or_else(|| vec![line.trim().to_string()]);
+                    cells.resize(alignments.len(), String::new());
+                    parse_cells(cells)
+                })
+                .collect();
+            Block::Table(Table {
+                header: parse_cells(header),
+                alignments,
+                rows,
+            })
+        }
+    };
+    Some(block)
+}
+
+/// Turns a block quote starting with a `[!NOTE]` line into an admonition.
+fn convert_block_quote(
+    mut children: Vec<Node>,
+    definitions: &Definitions,
+    extensions: Extensions,
+) -> Block {
+    let admonition = match children.first() {
+        _ if !extensions.admonitions => None,
+        Some(Node {
+            kind: Kind::Paragraph,
+            content,
+            ..
+        }) => {
+            let (first_line, rest) = content.split_once('\n').unwrap_or((content, ""));
+            parse_admonition(first_line).map(|admonition| (admonition, rest.to_string()))
+        }
+        _ => None,
+    };
+
+    let ((kind, title), rest) = match admonition {
+        Some(admonition) => admonition,
+        None => return Block::BlockQuote(convert_blocks(children, definitions, extensions)),
+    };
+
+    if rest.trim().is_empty() {
+        children.remove(0);
+    } else {
+        children[0].content = rest;
+    }
+    Block::Admonition {
+        kind,
+        title,
+        blocks: convert_blocks(children, definitions, extensions),
+    }
+}
+
+/// Splits a trailing `{#custom-id}` off the text of a heading.
+fn split_custom_id(text: &str) -> (&str, Option<String>) {
+    let text = text.trim();
+    let custom = text.strip_suffix('}').and_then(|rest| {
+        let start = rest.rfind("{#")?;
+        let (before, id) = (&rest[..start], &rest[start + 2..]);
+        let valid = !id.is_empty() && !id.contains(|c: char| c.is_whitespace() || c == '{');
+        let separated = before.is_empty() || before.ends_with(char::is_whitespace);
+        (valid && separated).then(|| (before.trim_end(), id.to_string()))
+    });
+    match custom {
+        Some((text, id)) => (text, Some(id)),
+        None => (text, None),
+    }
+}
+
+/// Gives headings without a custom id the slug of their text. Custom ids are
+/// reserved first, so generated ones never clash with them.
+fn assign_heading_ids(blocks: &mut [Block]) {
+    let mut slugger = Slugger::default();
+    visit_headings(blocks, &mut |id, _| {
+        if let Some(id) = id {
+            slugger.reserve(id);
+        }
+    });
+    visit_headings(blocks, &mut |id, content| {
+        if id.is_none() {
+            *id = Some(slugger.slug(&plain_text(content)));
+        }
+    });
+}
+
+fn visit_headings(blocks: &mut [Block], visit: &mut impl FnMut(&mut Option<String>, &[Inline])) {
+    for block in blocks {
+        match block {
+            Block::Heading { id, content, .. } => visit(id, content),
+            Block::List(list) => {
+                for item in &mut list.items {
+                    visit_headings(item, visit);
+                }
+            }
+            Block::BlockQuote(blocks) | Block::Admonition { blocks, .. } => {
+                visit_headings(blocks, visit)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Whether a paragraph is just a `[TOC]` or `[[_TOC_]]` placeholder.
+fn is_toc_placeholder(text: &str) -> bool {
+    matches!(text, "[TOC]" | "[[_TOC_]]")
+}
+
+fn fill_tables_of_contents(blocks: &mut [Block], toc: &[TocEntry]) {
+    for block in blocks {
+        match block {
+            Block::TableOfContents(entries) => *entries = toc.to_vec(),
+            Block::List(list) => {
+                for item in &mut list.items {
+                    fill_tables_of_contents(item, toc);
+                }
+            }
+            Block::BlockQuote(blocks) | Block::Admonition { blocks, .. } => {
+                fill_tables_of_contents(blocks, toc)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// A list is loose if any of its items, or any blocks directly inside them,
+/// are separated by blank lines.
+fn is_tight(items: &[Node]) -> bool {
+    let separated = |nodes: &[Node]| {
+        nodes
+            .windows(2)
+            .any(|pair| pair[0].end_line + 1 != pair[1].start_line)
+    };
+    !separated(items) && !items.iter().any(|item| separated(&item.children))
+}
+
+/// Parses the `[!NOTE]` line starting a GitHub-style callout, with an
+/// optional custom title after it.
+fn parse_admonition(line: &str) -> Option<(String, String)> {
+    let rest = line.trim().strip_prefix("[!")?;
+    let (kind, title) = rest.split_once(']')?;
+    let kind = kind.to_lowercase();
+    if !ADMONITIONS.contains(&kind.as_str()) {
+        return None;
+    }
+
+    let title = match title.trim() {
+        "" => {
+            let mut chars = kind.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+        title => title.to_string(),
+    };
+    Some((kind, title))
+}
+
+/// Returns the level and marker length, including the spaces after it, of
+/// an ATX heading such as `## Title`.
+fn atx_heading(line: &str) -> Option<(u8, usize)> {
+    let level = line.bytes().take_while(|&c| c == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let spaces = line[level..]
+        .bytes()
+        .take_while(|&c| c == b' ' || c == b'\t')
+        .count();
+    if spaces == 0 && level < line.len() {
+        return None;
+    }
+    Some((level as u8, level + spaces))
+}
+
+/// Removes the optional closing `###` of an ATX heading.
+fn strip_closing_sequence(content: &str) -> &str {
+    let trimmed = content.trim_end_matches([' ', '\t']);
+    let without_hashes = trimmed.trim_end_matches('#');
+    if without_hashes.len() == trimmed.len() {
+        trimmed
+    } else if without_hashes.trim_matches([' ', '\t']).is_empty() {
+        ""
+    } else if without_hashes.ends_with([' ', '\t']) {
+        without_hashes
+    } else {
+        trimmed
+    }
+}
+
+/// Returns the character and length of an opening code fence. Backtick
+/// fences can't have backticks in their info string.
+fn code_fence(line: &str) -> Option<(char, usize)> {
+    let fence = match line.bytes().next()? {
+        b'`' => '`',
+        b'~' => '~',
+        _ => return None,
+    };
+    let length = line.chars().take_while(|&c| c == fence).count();
+    if length < 3 || (fence == '`' && line[length..].contains('`')) {
+        return None;
+    }
+    Some((fence, length))
+}
+
+/// Returns the length of a closing code fence.
+fn closing_fence(line: &str, fence: char) -> Option<usize> {
+    let length = line.chars().take_while(|&c| c == fence).count();
+    if length < 3 || !line[length..].trim_matches([' ', '\t']).is_empty() {
+        return None;
+    }
+    Some(length)
+}
+
+fn setext_heading(line: &str) -> Option<u8> {
+    let line = line.trim_end_matches([' ', '\t']);
+    if !line.is_empty() && line.bytes().all(|c| c == b'=') {
+        Some(1)
+    } else if !line.is_empty() && line.bytes().all(|c| c == b'-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let marker = match line.bytes().next() {
+        Some(c @ (b'*' | b'-' | b'_')) => c,
+        _ => return false,
+    };
+    line.bytes().all(|c| c == marker || c == b' ' || c == b'\t')
+        && line.bytes().filter(|&c| c == marker).count() >= 3
+}
+
+/// Whether a line ends a table by starting another block.
+fn interrupts_table(line: &str) -> bool {
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    let after_marker = |length: usize| matches!(line.as_bytes().get(length), Some(b' ') | None);
+
+    line.starts_with('>')
+        || atx_heading(line).is_some()
+        || code_fence(line).is_some()
+        || is_thematic_break(line)
+        || (line.starts_with(['-', '*', '+']) && after_marker(1))
+        || (digits > 0 && line[digits..].starts_with(['.', ')']) && after_marker(digits + 1))
+}
+
+/// Returns which kind of HTML block a line starts, if any.
+fn html_block_start(line: &str) -> Option<u8> {
+    let lower = line.to_ascii_lowercase();
+    let tag_ends = |rest: &str| rest.is_empty() || rest.starts_with([' ', '\t', '>']);
+
+    for tag in RAW_TAGS {
+        if lower[1..].starts_with(tag) && tag_ends(&lower[1 + tag.len()..]) {
+            return Some(1);
+        }
+    }
+    if line.starts_with("<!--") {
+        return Some(2);
+    }
+    if line.starts_with("<?") {
+        return Some(3);
+    }
+    if line.starts_with("<![CDATA[") {
+        return Some(5);
+    }
+    if line.starts_with("<!") && line.as_bytes().get(2).is_some_and(u8::is_ascii_alphabetic) {
+        return Some(4);
+    }
+
+    let name_start = if lower.starts_with("</") { 2 } else { 1 };
+    let name_length = lower[name_start..]
+        .bytes()
+        .take_while(u8::is_ascii_alphanumeric)
+        .count();
+    let name = &lower[name_start..name_start + name_length];
+    let after = &lower[name_start + name_length..];
+    if BLOCK_TAGS.contains(&name) && (tag_ends(after) || after.starts_with("/>")) {
+        return Some(6);
+    }
+
+    let is_tag = line[name_start..].starts_with(|c: char| c.is_ascii_alphabetic());
+    match html_length(line.as_bytes(), &mut Unclosed::default()) {
+        Some(length) if is_tag && !RAW_TAGS.contains(&name) => {
+            if line[length..].trim().is_empty() {
+                Some(7)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether a line ends an HTML block of the first five kinds.
+fn html_block_ends(kind: u8, line: &str) -> bool {
+    match kind {
+        1 => {
+            let lower = line.to_ascii_lowercase();
+            RAW_TAGS
+                .iter()
+                .any(|tag| lower.contains(&format!("</{tag}>")))
+        }
+        2 => line.contains("-->"),
+        3 => line.contains("?>"),
+        4 => line.contains('>'),
+        5 => line.contains("]]>"),
+        _ => false,
+    }
+}
+
+/// Splits a table row on unescaped pipes, ignoring the optional leading and
+/// trailing pipe. Escaped pipes become part of the cell. Returns `None` for
+/// lines without any pipes.
+fn split_table_row(line: &str) -> Option<Vec<String>> {
+    let mut line = line.trim();
+    if !line.contains('|') {
+        return None;
+    }
+
+    line = line.strip_prefix('|').unwrap_or(line);
+    if line.ends_with('|') && !line.ends_with("\\|") {
+        line = &line[..line.len() - 1];
+    }
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    Some(cells)
+}
+
+fn parse_alignment(cell: &str) -> Option<Alignment> {
+    let left = cell.starts_with(':');
+    let right = cell.len() > 1 && cell.ends_with(':');
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.bytes().all(|c| c == b'-') {
+        return None;
+    }
+
+    Some(match (left, right) {
+        (true, true) => Alignment::Center,
+        (true, false) => Alignment::Left,
+        (false, true) => Alignment::Right,
+        (false, false) => Alignment::None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Inline;
+
+    fn blocks(markdown: &str) -> Vec<Block> {
+        parse(markdown).blocks
+    }
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.into())
+    }
+
+    fn paragraph(content: &str) -> Block {
+        Block::Paragraph(vec![text(content)])
+    }
+
+    fn heading(level: u8, id: &str, content: &str) -> Block {
+        Block::Heading {
+            level,
+            id: Some(id.into()),
+            content: vec![text(content)],
+        }
+    }
+
+    fn list(kind: ListKind, tight: bool, items: Vec<Vec<Block>>) -> Block {
+        Block::List(List { kind, tight, items })
+    }
+
+    #[test]
+    fn headings() {
+        assert_eq!(
+            blocks("# One\n## Two ##\n###### Six\n####### Seven\n\nTitle\n=====\nSub\n---"),
+            vec![
+                heading(1, "one", "One"),
+                heading(2, "two", "Two"),
+                heading(6, "six", "Six"),
+                paragraph("####### Seven"),
+                heading(1, "title", "Title"),
+                heading(2, "sub", "Sub"),
+            ]
+        );
+    }
+
+    #[test]
+    fn heading_ids() {
+        assert_eq!(
+            blocks(concat!(
+                "# Intro\n",
+                "## Intro\n",
+                "- ## Intro\n\n",
+                "> Setup {#intro-1}\n> ---\n\n",
+                "### Install {#install} ###\n",
+                "# \\{#not-an-id}\n",
+                "# 🎉\n",
+                "# !!!\n",
+            )),
+            vec![
+                heading(1, "intro", "Intro"),
+                heading(2, "intro-2", "Intro"),
+                list(
+                    ListKind::Unordered,
+                    true,
+                    vec![vec![heading(2, "intro-3", "Intro")]]
+                ),
+                Block::BlockQuote(vec![heading(2, "intro-1", "Setup")]),
+                heading(3, "install", "Install"),
+                heading(1, "not-an-id", "{#not-an-id}"),
+                heading(1, "section", "🎉"),
+                heading(1, "section-1", "!!!"),
+            ]
+        );
+    }
+
+    #[test]
+    fn toc_placeholders() {
+        let toc = vec![TocEntry {
+            level: 1,
+            id: "title".into(),
+            text: "Title".into(),
+            children: vec![],
+        }];
+        assert_eq!(
+            blocks("[TOC]\n\n# Title\n\n> [[_TOC_]]\n\n[toc]\n\n[TOC] here\n\n[toc]: /toc"),
+            vec![
+                Block::TableOfContents(toc.clone()),
+                heading(1, "title", "Title"),
+                Block::BlockQuote(vec![Block::TableOfContents(toc)]),
+                Block::Paragraph(vec![Inline::Link {
+                    url: "/toc".into(),
+                    title: None,
+                    content: vec![text("toc")],
+                }]),
+                Block::Paragraph(vec![
+                    Inline::Link {
+                        url: "/toc".into(),
+                        title: None,
+                        content: vec![text("TOC")],
+                    },
+                    text(" here"),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(
+            blocks(
+                "```rust extra\nfn main() {}\n\n```\n~~~\n```\n~~~\n\n    indented\n\n    more\n"
+            ),
+            vec![
+                Block::CodeBlock {
+                    language: Some("rust".into()),
+                    code: "fn main() {}\n\n".into(),
+                },
+                Block::CodeBlock {
+                    language: None,
+                    code: "```\n".into(),
+                },
+                Block::CodeBlock {
+                    language: None,
+                    code: "indented\n\nmore\n".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_code_block() {
+        assert_eq!(
+            blocks("```\nHello\r\nWorld!"),
+            vec![Block::CodeBlock {
+                language: None,
+                code: "Hello\nWorld!\n".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unordered_lists() {
+        assert_eq!(
+            blocks("- abc\n  - def\n- ghi\n+ other\n\n-no list"),
+            vec![
+                list(
+                    ListKind::Unordered,
+                    true,
+                    vec![
+                        vec![
+                            paragraph("abc"),
+                            list(ListKind::Unordered, true, vec![vec![paragraph("def")]]),
+                        ],
+                        vec![paragraph("ghi")],
+                    ]
+                ),
+                list(ListKind::Unordered, true, vec![vec![paragraph("other")]]),
+                paragraph("-no list"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ordered_lists() {
+        assert_eq!(
+            blocks("3. Three\n4. Four\n1) Other\n\nIn 2024. we\n1. interrupt"),
+            vec![
+                list(
+                    ListKind::Ordered(3),
+                    true,
+                    vec![vec![paragraph("Three")], vec![paragraph("Four")]]
+                ),
+                list(ListKind::Ordered(1), true, vec![vec![paragraph("Other")]]),
+                paragraph("In 2024. we"),
+                list(
+                    ListKind::Ordered(1),
+                    true,
+                    vec![vec![paragraph("interrupt")]]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn loose_lists() {
+        assert_eq!(
+            blocks("- a\n\n- b\n\n* c\n\n  d\n\n- e\n  ```\n\n  ```\n"),
+            vec![
+                list(
+                    ListKind::Unordered,
+                    false,
+                    vec![vec![paragraph("a")], vec![paragraph("b")]]
+                ),
+                list(
+                    ListKind::Unordered,
+                    false,
+                    vec![vec![paragraph("c"), paragraph("d")]]
+                ),
+                list(
+                    ListKind::Unordered,
+                    true,
+                    vec![vec![
+                        paragraph("e"),
+                        Block::CodeBlock {
+                            language: None,
+                            code: "\n".into(),
+                        },
+                    ]]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn block_quotes() {
+        assert_eq!(
+            blocks("> Quoted\nlazy\n> > Nested\n\n>     code\n"),
+            vec![
+                Block::BlockQuote(vec![
+                    Block::Paragraph(vec![text("Quoted"), Inline::SoftBreak, text("lazy")]),
+                    Block::BlockQuote(vec![paragraph("Nested")]),
+                ]),
+                Block::BlockQuote(vec![Block::CodeBlock {
+                    language: None,
+                    code: "code\n".into(),
+                }]),
+            ]
+        );
+    }
+
+    #[test]
+    fn admonitions() {
+        assert_eq!(
+            blocks("> [!WARNING] Careful now\n> Hot.\n\n> [!TIP]\n\n> [!NOPE]\n> x"),
+            vec![
+                Block::Admonition {
+                    kind: "warning".into(),
+                    title: "Careful now".into(),
+                    blocks: vec![paragraph("Hot.")],
+                },
+                Block::Admonition {
+                    kind: "tip".into(),
+                    title: "Tip".into(),
+                    blocks: vec![],
+                },
+                Block::BlockQuote(vec![Block::Paragraph(vec![
+                    text("[!NOPE]"),
+                    Inline::SoftBreak,
+                    text("x"),
+                ])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let cell = |content: &str| vec![text(content)];
+        assert_eq!(
+            blocks("| a | b |\n|:--|--:|\n| 1 |\n| 2 | 3 | 4 |\n- after"),
+            vec![
+                Block::Table(Table {
+                    alignments: vec![Alignment::Left, Alignment::Right],
+                    header: vec![cell("a"), cell("b")],
+                    rows: vec![vec![cell("1"), vec![]], vec![cell("2"), cell("3")],],
+                }),
+                list(ListKind::Unordered, true, vec![vec![paragraph("after")]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_a_table() {
+        assert_eq!(
+            blocks("a | b\n|---|\n\nno pipes\n---|---"),
+            vec![
+                Block::Paragraph(vec![text("a | b"), Inline::SoftBreak, text("|---|")]),
+                Block::Paragraph(vec![text("no pipes"), Inline::SoftBreak, text("---|---")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn html_blocks() {
+        assert_eq!(
+            blocks("<div>\n*hi*\n\n<!-- a\n\nb -->\ntext <span>x</span>\n<span>\nnot html\n"),
+            vec![
+                Block::Html("<div>\n*hi*".into()),
+                Block::Html("<!-- a\n\nb -->".into()),
+                Block::Paragraph(vec![
+                    text("text "),
+                    Inline::Html("<span>".into()),
+                    text("x"),
+                    Inline::Html("</span>".into()),
+                    Inline::SoftBreak,
+                    Inline::Html("<span>".into()),
+                    Inline::SoftBreak,
+                    text("not html"),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn link_definitions() {
+        let link = |url: &str, title: Option<&str>, content: &str| Inline::Link {
+            url: url.into(),
+            title: title.map(Into::into),
+            content: vec![text(content)],
+        };
+        assert_eq!(
+            blocks("[a] [b]\n\n> [a]: /first\n\n[A]: /second\n[b]:\n<my url> 'B'\n[c]: /c \"x\" y"),
+            vec![
+                Block::Paragraph(vec![
+                    link("/first", None, "a"),
+                    text(" "),
+                    link("my url", Some("B"), "b"),
+                ]),
+                Block::BlockQuote(vec![]),
+                paragraph("[c]: /c \"x\" y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn thematic_breaks() {
+        assert_eq!(
+            blocks("***\n- - -\n__\n\nText\n***"),
+            vec![
+                Block::ThematicBreak,
+                Block::ThematicBreak,
+                paragraph("__"),
+                paragraph("Text"),
+                Block::ThematicBreak,
+            ]
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let start = std::time::Instant::now();
+        let list: String = (0..1000)
+            .map(|depth| format!("{}- a\n", "  ".repeat(depth)))
+            .collect();
+        let mut blocks = blocks(&list);
+        let mut depth = 0;
+        while let Some(Block::List(list)) = blocks.last() {
+            blocks = list.items[0].clone();
+            depth += 1;
+        }
+        // Each level is a list and its item, below the document and above
+        // the paragraph of the last item
+        assert_eq!(depth, MAX_NESTING / 2 - 1);
+
+        let quotes = parse(&">".repeat(50000));
+        assert_eq!(quotes.blocks.len(), 1);
+        let elapsed = start.elapsed();
+        assert!(elapsed.as_secs() < 2, "{elapsed:?}");
+    }
+}
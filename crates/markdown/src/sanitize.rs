@@ -0,0 +1,435 @@
+use crate::entities::decode_entities;
+
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "tt",
+    "u",
+    "ul",
+    "var",
+];
+
+const ALLOWED_ATTRIBUTES: &[&str] = &[
+    "align", "alt", "class", "colspan", "dir", "height", "href", "id", "lang", "name", "open",
+    "rowspan", "span", "src", "start", "title", "width",
+];
+
+const URL_ATTRIBUTES: &[&str] = &["href", "src"];
+
+const SAFE_SCHEMES: &[&str] = &["http", "https", "mailto", "tel"];
+
+struct Tag {
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+/// How many bytes at the end of the input are known to have no `-->`,
+/// `]]>`, `?>` or `>` in them. Callers looking at every `<` of the same input
+/// share one, so unclosed comments and the like are only searched once.
+#[derive(Debug, Default)]
+pub struct Unclosed {
+    comment: usize,
+    cdata: usize,
+    instruction: usize,
+    declaration: usize,
+}
+
+/// Returns the length of the HTML tag, comment or declaration at the start of
+/// `input`, or `None` if it doesn't start with one. `input` has to be the
+/// rest of the input `unclosed` was used with before.
+pub fn html_length(input: &[u8], unclosed: &mut Unclosed) -> Option<usize> {
+    for empty_comment in [b"<!-->".as_slice(), b"<!--->"] {
+        if input.starts_with(empty_comment) {
+            return Some(empty_comment.len());
+        }
+    }
+    if input.starts_with(b"<!--") {
+        let end = find(input, 4, b"-->", &mut unclosed.comment)?;
+        return Some(end + 3);
+    }
+    if input.starts_with(b"<![CDATA[") {
+        let end = find(input, 9, b"]]>", &mut unclosed.cdata)?;
+        return Some(end + 3);
+    }
+    if input.starts_with(b"<?") {
+        let end = find(input, 2, b"?>", &mut unclosed.instruction)?;
+        return Some(end + 2);
+    }
+    if input.starts_with(b"<!") && input.get(2).is_some_and(u8::is_ascii_alphabetic) {
+        let end = find(input, 2, b">", &mut unclosed.declaration)?;
+        return Some(end + 1);
+    }
+    parse_tag(input).map(|(_, length)| length)
+}
+
+/// Finds `needle` from `start`, unless the last `missing` bytes are known not
+/// to have it and `start` is among them.
+fn find(input: &[u8], start: usize, needle: &[u8], missing: &mut usize) -> Option<usize> {
+    let searched = input.len().checked_sub(start)?;
+    if searched <= *missing {
+        return None;
+    }
+    let found = input[start..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|offset| start + offset);
+    if found.is_none() {
+        *missing = searched;
+    }
+    found
+}
+
+/// Parses an opening or closing tag such as `<a href="x">` or `</div>`,
+/// returning it together with its length in bytes.
+fn parse_tag(input: &[u8]) -> Option<(Tag, usize)> {
+    let mut i = 1;
+    let closing = input.get(i) == Some(&b'/');
+    if closing {
+        i += 1;
+    }
+
+    let name_start = i;
+    if !input.get(i)?.is_ascii_alphabetic() {
+        return None;
+    }
+    while input
+        .get(i)
+        .is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'-')
+    {
+        i += 1;
+    }
+    let name = String::from_utf8_lossy(&input[name_start..i]).to_ascii_lowercase();
+
+    let mut attributes = Vec::new();
+    loop {
+        let before_whitespace = i;
+        while input.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+
+        match input.get(i)? {
+            b'>' => {
+                let tag = Tag {
+                    name,
+                    closing,
+                    self_closing: false,
+                    attributes,
+                };
+                return Some((tag, i + 1));
+            }
+            b'/' if !closing && input.get(i + 1) == Some(&b'>') => {
+                let tag = Tag {
+                    name,
+                    closing,
+                    self_closing: true,
+                    attributes,
+                };
+                return Some((tag, i + 2));
+            }
+            _ if closing || i == before_whitespace => return None,
+            _ => {
+                let (attribute, length) = parse_attribute(&input[i..])?;
+                attributes.push(attribute);
+                i += length;
+            }
+        }
+    }
+}
+
+fn parse_attribute(input: &[u8]) -> Option<((String, Option<String>), usize)> {
+    let is_name = |c: &u8| c.is_ascii_alphanumeric() || matches!(c, b'_' | b':' | b'.' | b'-');
+    if !input
+        .first()
+        .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, b'_' | b':'))
+    {
+        return None;
+    }
+
+    let mut i = 0;
+    while input.get(i).is_some_and(is_name) {
+        i += 1;
+    }
+    let name = String::from_utf8_lossy(&input[..i]).to_ascii_lowercase();
+
+    let mut j = i;
+    while input.get(j).is_some_and(u8::is_ascii_whitespace) {
+        j += 1;
+    }
+    if input.get(j) != Some(&b'=') {
+        return Some(((name, None), i));
+    }
+
+    j += 1;
+    while input.get(j).is_some_and(u8::is_ascii_whitespace) {
+        j += 1;
+    }
+
+    let (value, end) = match input.get(j)? {
+        quote @ (b'"' | b'\'') => {
+            let close = j + 1 + input[j + 1..].iter().position(|c| c == quote)?;
+            (&input[j + 1..close], close + 1)
+        }
+        _ => {
+            let start = j;
+            while input
+                .get(j)
+                .is_some_and(|c| !c.is_ascii_whitespace() && !b"\"'=<>`".contains(c))
+            {
+                j += 1;
+            }
+            if j == start {
+                return None;
+            }
+            (&input[start..j], j)
+        }
+    };
+
+    let value = String::from_utf8_lossy(value).to_string();
+    Some(((name, Some(value)), end))
+}
+
+/// Rewrites raw HTML so only allow-listed tags and attributes survive, with
+/// URLs limited to safe schemes. Other tags are escaped so they show up as
+/// text, and comments are dropped.
+pub fn sanitize_html(html: &str) -> String {
+    let input = html.as_bytes();
+    let mut output = String::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    let mut unclosed = Unclosed::default();
+
+    while i < input.len() {
+        if input[i] != b'<' {
+            i += 1;
+            continue;
+        }
+
+        output.push_str(&html[text_start..i]);
+        match html_length(&input[i..], &mut unclosed) {
+            Some(length) => {
+                let raw = &html[i..i + length];
+                if let Some((tag, _)) = parse_tag(raw.as_bytes()) {
+                    match sanitize_tag(&tag) {
+                        Some(tag) => output.push_str(&tag),
+                        None => output.push_str(&escape_html(raw)),
+                    }
+                }
+                i += length;
+            }
+            None => {
+                output.push_str("&lt;");
+                i += 1;
+            }
+        }
+        text_start = i;
+    }
+
+    output.push_str(&html[text_start..]);
+    output
+}
+
+fn sanitize_tag(tag: &Tag) -> Option<String> {
+    let name = tag.name.as_str();
+    if !ALLOWED_TAGS.contains(&name) {
+        return None;
+    }
+
+    if tag.closing {
+        return Some(format!("</{name}>"));
+    }
+
+    let mut output = format!("<{name}");
+    for (attribute, value) in &tag.attributes {
+        if !ALLOWED_ATTRIBUTES.contains(&attribute.as_str()) {
+            continue;
+        }
+        match value {
+            Some(value) => {
+                let value = decode_entities(value);
+                if URL_ATTRIBUTES.contains(&attribute.as_str()) && !is_safe_url(&value) {
+                    continue;
+                }
+                output.push(' ');
+                output.push_str(attribute);
+                output.push_str("=\"");
+                output.push_str(&escape_attribute(&value));
+                output.push('"');
+            }
+            None => {
+                output.push(' ');
+                output.push_str(attribute);
+            }
+        }
+    }
+    if tag.self_closing {
+        output.push_str(" /");
+    }
+    output.push('>');
+    Some(output)
+}
+
+/// Whether a URL is relative or uses a scheme that can't run script.
+pub fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+
+    match url.find([':', '/', '?', '#']) {
+        Some(index) if url[index..].starts_with(':') => {
+            let scheme = url[..index].to_ascii_lowercase();
+            SAFE_SCHEMES.contains(&scheme.as_str())
+        }
+        _ => true,
+    }
+}
+
+/// Escapes text, where everything is shown literally.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    escape_html(value).replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_lengths() {
+        assert_eq!(
+            html_length(b"<a href=\"x\">link</a>", &mut Unclosed::default()),
+            Some(12)
+        );
+        assert_eq!(
+            html_length(b"</a> after", &mut Unclosed::default()),
+            Some(4)
+        );
+        assert_eq!(html_length(b"<br/>", &mut Unclosed::default()), Some(5));
+        assert_eq!(
+            html_length(b"<img src=x alt='a b' hidden>", &mut Unclosed::default()),
+            Some(28)
+        );
+        assert_eq!(
+            html_length(b"<!-- note -->", &mut Unclosed::default()),
+            Some(13)
+        );
+        assert_eq!(html_length(b"<!--> -->", &mut Unclosed::default()), Some(5));
+        assert_eq!(
+            html_length(b"<![CDATA[>&<]]>", &mut Unclosed::default()),
+            Some(15)
+        );
+        assert_eq!(html_length(b"< b", &mut Unclosed::default()), None);
+        assert_eq!(html_length(b"<a", &mut Unclosed::default()), None);
+        assert_eq!(html_length(b"<a\"x\">", &mut Unclosed::default()), None);
+        assert_eq!(html_length(b"<1>", &mut Unclosed::default()), None);
+    }
+
+    #[test]
+    fn sanitize() {
+        assert_eq!(
+            sanitize_html("<a href=\"https://example.com\" onclick=\"evil()\">x</a>"),
+            "<a href=\"https://example.com\">x</a>"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\" JaVa\tScript:alert(1)\">x</a>"),
+            "<a>x</a>"
+        );
+        assert_eq!(
+            sanitize_html("<script>alert(1)</script>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(
+            sanitize_html("<details open><summary>More</summary><!-- hidden -->1 < 2</details>"),
+            "<details open><summary>More</summary>1 &lt; 2</details>"
+        );
+        assert_eq!(
+            sanitize_html("<img src=\"a.png\" style=\"x\"/>"),
+            "<img src=\"a.png\" />"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"?a=1&amp;b=2\" title=\"Tom &amp; Jerry &quot;\">x</a>"),
+            "<a href=\"?a=1&amp;b=2\" title=\"Tom &amp; Jerry &quot;\">x</a>"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"javascript&#58;alert(1)\">x</a>"),
+            "<a>x</a>"
+        );
+    }
+
+    #[test]
+    fn safe_urls() {
+        assert!(is_safe_url("guides/setup.html"));
+        assert!(is_safe_url("/a:b"));
+        assert!(is_safe_url("HTTPS://example.com"));
+        assert!(is_safe_url("mailto:someone@example.com"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url("data:text/html;base64,AAAA"));
+        assert!(!is_safe_url("vbscript:msgbox"));
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(escape_html("&copy; <b>"), "&amp;copy; &lt;b&gt;");
+        assert_eq!(escape_attribute("\"a\" & b"), "&quot;a&quot; &amp; b");
+    }
+}
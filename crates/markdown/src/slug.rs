@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+/// Turns heading text into the anchor GitHub would give it: lowercased, with
+/// spaces turned into `-` and punctuation and symbols dropped. Letters and
+/// digits of any script are kept.
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || is_mark(c) || is_connector(c) => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Combining marks, such as the accents of decomposed letters.
+fn is_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{300}'..='\u{36F}'
+            | '\u{483}'..='\u{489}'
+            | '\u{591}'..='\u{5BD}'
+            | '\u{93C}'..='\u{94D}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{3099}'..='\u{309A}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Connector punctuation, such as `_`.
+fn is_connector(c: char) -> bool {
+    matches!(
+        c,
+        '_' | '\u{203F}'..='\u{2040}'
+            | '\u{2054}'
+            | '\u{FE33}'..='\u{FE34}'
+            | '\u{FE4D}'..='\u{FE4F}'
+            | '\u{FF3F}'
+    )
+}
+
+/// The slug of headings that have nothing left after slugifying, such as
+/// `# !!!` or a lone emoji.
+const FALLBACK: &str = "section";
+
+/// Hands out slugs that are unique within a page, numbering repeats the way
+/// GitHub does: `intro`, `intro-1`, `intro-2`.
+#[derive(Debug, Default)]
+pub struct Slugger {
+    occurrences: HashMap<String, usize>,
+}
+
+impl Slugger {
+    /// Returns the slug for `text`, or a numbered `section` if nothing of it
+    /// is left after slugifying.
+    pub fn slug(&mut self, text: &str) -> String {
+        let mut base = slugify(text);
+        if base.is_empty() {
+            base = FALLBACK.to_string();
+        }
+
+        let mut slug = base.clone();
+        while self.occurrences.contains_key(&slug) {
+            let count = self.occurrences.entry(base.clone()).or_default();
+            *count += 1;
+            slug = format!("{base}-{count}");
+        }
+        self.occurrences.insert(slug.clone(), 0);
+        slug
+    }
+
+    /// Marks an id chosen by the author as taken, so generated slugs avoid it.
+    pub fn reserve(&mut self, id: &str) {
+        self.occurrences.entry(id.to_string()).or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_slugs() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(slugify("What's new?"), "whats-new");
+        assert_eq!(slugify("C++ & Rust"), "c--rust");
+        assert_eq!(
+            slugify("snake_case and kebab-case"),
+            "snake_case-and-kebab-case"
+        );
+        assert_eq!(slugify("v1.2: Release notes!"), "v12-release-notes");
+        assert_eq!(slugify("🎉 Party"), "-party");
+    }
+
+    #[test]
+    fn unicode() {
+        assert_eq!(slugify("Über Straße"), "über-straße");
+        assert_eq!(slugify("Ελληνικά Κείμενα"), "ελληνικά-κείμενα");
+        assert_eq!(slugify("日本語の見出し"), "日本語の見出し");
+        assert_eq!(slugify("Cafe\u{301}"), "cafe\u{301}");
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut slugger = Slugger::default();
+        assert_eq!(slugger.slug("Intro"), "intro");
+        assert_eq!(slugger.slug("Intro"), "intro-1");
+        assert_eq!(slugger.slug("Intro 1"), "intro-1-1");
+        assert_eq!(slugger.slug("Intro"), "intro-2");
+        assert_eq!(slugger.slug("!!!"), "section");
+        assert_eq!(slugger.slug("🎉"), "section-1");
+        assert_eq!(slugger.slug("Section"), "section-2");
+
+        slugger.reserve("setup");
+        assert_eq!(slugger.slug("Setup"), "setup-1");
+    }
+}
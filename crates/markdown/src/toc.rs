@@ -0,0 +1,115 @@
+use crate::ast::{plain_text, Block, Document};
+
+/// A heading in a table of contents, with the headings of lower levels that
+/// follow it nested inside.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub text: String,
+    pub children: Vec<TocEntry>,
+}
+
+/// Builds the table of contents of a document from its headings. Headings
+/// nest under the closest heading of a higher level before them, and ones
+/// without an id are left out as nothing could link to them.
+pub fn table_of_contents(document: &Document) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    collect_entries(&document.blocks, &mut entries);
+    nest(&mut entries.into_iter().peekable(), 0)
+}
+
+fn collect_entries(blocks: &[Block], entries: &mut Vec<TocEntry>) {
+    for block in blocks {
+        match block {
+            Block::Heading {
+                level,
+                id: Some(id),
+                content,
+            } => entries.push(TocEntry {
+                level: *level,
+                id: id.clone(),
+                text: plain_text(content),
+                children: Vec::new(),
+            }),
+            Block::List(list) => {
+                for item in &list.items {
+                    collect_entries(item, entries);
+                }
+            }
+            Block::BlockQuote(blocks) | Block::Admonition { blocks, .. } => {
+                collect_entries(blocks, entries)
+            }
+            _ => (),
+        }
+    }
+}
+
+fn nest(
+    entries: &mut std::iter::Peekable<std::vec::IntoIter<TocEntry>>,
+    parent_level: u8,
+) -> Vec<TocEntry> {
+    let mut nested = Vec::new();
+    while let Some(mut entry) = entries.next_if(|entry| entry.level > parent_level) {
+        entry.children = nest(entries, entry.level);
+        nested.push(entry);
+    }
+    nested
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn entry(level: u8, id: &str, children: Vec<TocEntry>) -> TocEntry {
+        TocEntry {
+            level,
+            id: id.into(),
+            text: id.into(),
+            children,
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let document = parse("## a\n### b\n#### c\n### d\n## e\n#### f\n# g\n## h");
+        assert_eq!(
+            table_of_contents(&document),
+            vec![
+                entry(
+                    2,
+                    "a",
+                    vec![
+                        entry(3, "b", vec![entry(4, "c", vec![])]),
+                        entry(3, "d", vec![])
+                    ]
+                ),
+                entry(2, "e", vec![entry(4, "f", vec![])]),
+                entry(1, "g", vec![entry(2, "h", vec![])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_and_ids() {
+        let document = parse("# The *`main`* loop {#loop}\n\n> ## quoted\n\n# ???");
+        assert_eq!(
+            table_of_contents(&document),
+            vec![
+                TocEntry {
+                    level: 1,
+                    id: "loop".into(),
+                    text: "The main loop".into(),
+                    children: vec![entry(2, "quoted", vec![])],
+                },
+                TocEntry {
+                    level: 1,
+                    id: "section".into(),
+                    text: "???".into(),
+                    children: vec![],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+/// Whether `url` points somewhere relative to the current page, as opposed to
+/// an absolute path (`/docs`), a protocol-relative URL (`//host`), a URL with
+/// a scheme (`https:`, `mailto:`) or a fragment on the same page (`#top`).
+pub fn is_relative(url: &str) -> bool {
+    !(url.is_empty() || url.starts_with('/') || url.starts_with('#') || has_scheme(url))
+}
+
+fn has_scheme(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            let mut chars = scheme.chars();
+            chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        None => false,
+    }
+}
+
+/// Points relative links to markdown files at the `.html` files generated for
+/// them, keeping any query and fragment.
+pub fn rewrite_markdown_link(url: &str) -> String {
+    if !is_relative(url) {
+        return url.to_string();
+    }
+
+    let split = url.find(['?', '#']).unwrap_or(url.len());
+    let (path, rest) = url.split_at(split);
+    let lowercase = path.to_ascii_lowercase();
+
+    for extension in [".md", ".markdown"] {
+        if lowercase.ends_with(extension) {
+            let stem = &path[..path.len() - extension.len()];
+            return format!("{stem}.html{rest}");
+        }
+    }
+
+    url.to_string()
+}
+
+/// Percent-encodes the characters of a URL that can't appear in an `href`
+/// as they are, such as spaces and non-ASCII characters. Existing escapes are
+/// kept.
+pub fn encode_url(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut output = String::with_capacity(url.len());
+    for (i, &c) in bytes.iter().enumerate() {
+        let is_escape = c == b'%'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+            && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit);
+        if c.is_ascii_alphanumeric() || b";/?:@&=+$,-_.!~*'()#".contains(&c) || is_escape {
+            output.push(c as char);
+        } else {
+            output.push_str(&format!("%{c:02X}"));
+        }
+    }
+    output
+}
+
+/// Percent-encodes a file or directory name for one segment of a URL path,
+/// so characters with a meaning in URLs, such as `#`, `?`, `%`, `/` and `:`,
+/// stay part of the name.
+pub fn encode_path_segment(segment: &str) -> String {
+    let mut output = String::with_capacity(segment.len());
+    for c in segment.bytes() {
+        if c.is_ascii_alphanumeric() || b"-_.~!$&'()*+,;=@".contains(&c) {
+            output.push(c as char);
+        } else {
+            output.push_str(&format!("%{c:02X}"));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative() {
+        assert!(is_relative("setup.md"));
+        assert!(is_relative("../guides/setup.md"));
+        assert!(is_relative("img/a:b.png"));
+        assert!(!is_relative("/setup.md"));
+        assert!(!is_relative("//example.com/setup.md"));
+        assert!(!is_relative("https://example.com/setup.md"));
+        assert!(!is_relative("mailto:someone@example.com"));
+        assert!(!is_relative("#setup"));
+    }
+
+    #[test]
+    fn rewrite() {
+        assert_eq!(
+            rewrite_markdown_link("../guides/setup.md"),
+            "../guides/setup.html"
+        );
+        assert_eq!(
+            rewrite_markdown_link("setup.MD#install"),
+            "setup.html#install"
+        );
+        assert_eq!(
+            rewrite_markdown_link("notes.markdown?raw=1#top"),
+            "notes.html?raw=1#top"
+        );
+        assert_eq!(rewrite_markdown_link("setup.md.txt"), "setup.md.txt");
+        assert_eq!(rewrite_markdown_link("guides/"), "guides/");
+        assert_eq!(
+            rewrite_markdown_link("https://example.com/README.md"),
+            "https://example.com/README.md"
+        );
+        assert_eq!(rewrite_markdown_link("/README.md"), "/README.md");
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(encode_url("a b/ü.png"), "a%20b/%C3%BC.png");
+        assert_eq!(encode_url("/search?q=a&b=%20#x"), "/search?q=a&b=%20#x");
+        assert_eq!(encode_url("\"[50%]\""), "%22%5B50%25%5D%22");
+        assert_eq!(
+            encode_path_segment("C# 50%?/ü:x.html"),
+            "C%23%2050%25%3F%2F%C3%BC%3Ax.html"
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Runs the CommonMark spec examples in `spec.txt` and reports how many pass
+//! in each section. Run with `cargo test -p markdown --test spec -- --nocapture`
+//! to see the report.
+
+use markdown::{to_html_with_options, Options, RawHtml};
+
+/// Sections every example of which has to pass.
+const REQUIRED_SECTIONS: &[&str] = &[
+    "Tabs",
+    "Precedence",
+    "Thematic breaks",
+    "ATX headings",
+    "Setext headings",
+    "Indented code blocks",
+    "Fenced code blocks",
+    "HTML blocks",
+    "Link reference definitions",
+    "Paragraphs",
+    "Blank lines",
+    "Block quotes",
+    "List items",
+    "Lists",
+    "Backslash escapes",
+    "Entity and numeric character references",
+    "Code spans",
+    "Emphasis and strong emphasis",
+    "Links",
+    "Images",
+    "Autolinks",
+    "Raw HTML",
+    "Hard line breaks",
+    "Soft line breaks",
+    "Textual content",
+];
+
+/// Tags around which whitespace doesn't matter.
+const BLOCK_TAGS: &[&str] = &[
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+const FENCE: &str = "````````````````````````````````";
+
+struct Example {
+    section: String,
+    line: usize,
+    markdown: String,
+    html: String,
+}
+
+fn examples(spec: &str) -> Vec<Example> {
+    let mut examples = Vec::new();
+    let mut section = String::new();
+    let mut lines = spec.lines().enumerate();
+
+    while let Some((number, line)) = lines.next() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            section = heading.to_string();
+            continue;
+        }
+        if line != format!("{FENCE} example") {
+            continue;
+        }
+
+        let mut markdown = String::new();
+        let mut html = String::new();
+        let mut in_html = false;
+        for (_, line) in lines.by_ref() {
+            if line == FENCE {
+                break;
+            }
+            if line == "." && !in_html {
+                in_html = true;
+                continue;
+            }
+            let text = if in_html { &mut html } else { &mut markdown };
+            text.push_str(&line.replace('→', "\t"));
+            text.push('\n');
+        }
+
+        examples.push(Example {
+            section: section.clone(),
+            line: number + 1,
+            markdown,
+            html,
+        });
+    }
+
+    examples
+}
+
+enum Token<'a> {
+    Tag { name: String, source: &'a str },
+    Text(&'a str),
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while let Some(c) = html[i..].chars().next() {
+        let is_tag =
+            c == '<' && html[i + 1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/');
+        let end = if is_tag { html[i..].find('>') } else { None };
+        let Some(end) = end else {
+            i += c.len_utf8();
+            continue;
+        };
+
+        if text_start < i {
+            tokens.push(Token::Text(&html[text_start..i]));
+        }
+        let source = &html[i..=i + end];
+        let name = source
+            .trim_start_matches(['<', '/'])
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        tokens.push(Token::Tag { name, source });
+        i += end + 1;
+        text_start = i;
+    }
+    if text_start < html.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+/// Normalizes the HTML so that renderings that only differ in unimportant
+/// ways compare equal: whitespace next to block tags is dropped, other runs
+/// of whitespace outside `<pre>` become one space, `<br />` and `<br>` are
+/// the same, and so are `&quot;` and `"` in text. The ids wikiup gives
+/// headings aren't part of the spec, so they are dropped too.
+fn normalize(html: &str) -> String {
+    let tokens = tokenize(html);
+    let is_block = |token: Option<&Token>| match token {
+        Some(Token::Tag { name, .. }) => BLOCK_TAGS.contains(&name.as_str()),
+        Some(Token::Text(_)) => false,
+        None => true,
+    };
+
+    let mut output = String::new();
+    let mut in_pre = false;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Tag { name, source } => {
+                if name == "pre" {
+                    in_pre = !source.starts_with("</");
+                }
+                let source = source.trim_end_matches('>').trim_end_matches('/');
+                let is_heading = matches!(name.as_bytes(), [b'h', b'1'..=b'6']);
+                match source.split_once(" id=\"") {
+                    Some((tag, _)) if is_heading => output.push_str(tag),
+                    _ => output.push_str(source.trim_end()),
+                }
+                output.push('>');
+            }
+            Token::Text(text) if in_pre => output.push_str(&text.replace("&quot;", "\"")),
+            Token::Text(text) => {
+                let is_space = |c: char| c.is_ascii_whitespace();
+                let leading = text.starts_with(is_space)
+                    && !is_block(i.checked_sub(1).and_then(|i| tokens.get(i)));
+                let trailing = text.ends_with(is_space) && !is_block(tokens.get(i + 1));
+                let words = text.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+                if words.is_empty() {
+                    if leading && trailing {
+                        output.push(' ');
+                    }
+                    continue;
+                }
+                if leading {
+                    output.push(' ');
+                }
+                output.push_str(&words.replace("&quot;", "\""));
+                if trailing {
+                    output.push(' ');
+                }
+            }
+        }
+    }
+    output
+}
+
+#[test]
+fn commonmark_spec() {
+    let spec = include_str!("spec.txt");
+    let options = Options {
+        raw_html: RawHtml::PassThrough,
+        ..Options::default()
+    };
+
+    let mut sections: Vec<(String, usize, usize)> = Vec::new();
+    let mut failures = Vec::new();
+    for example in examples(spec) {
+        let actual = to_html_with_options(&example.markdown, &options);
+        let passed = normalize(&actual) == normalize(&example.html);
+
+        match sections.last_mut() {
+            Some((section, passes, total)) if *section == example.section => {
+                *passes += usize::from(passed);
+                *total += 1;
+            }
+            _ => sections.push((example.section.clone(), usize::from(passed), 1)),
+        }
+        if !passed {
+            failures.push((example, actual));
+        }
+    }
+
+    for (section, passes, total) in &sections {
+        println!("{section:<45} {passes:>3}/{total}");
+    }
+
+    let required = failures
+        .iter()
+        .filter(|(example, _)| REQUIRED_SECTIONS.contains(&example.section.as_str()))
+        .collect::<Vec<_>>();
+    for (example, actual) in &required {
+        println!(
+            "\nspec.txt:{} ({})\n--- markdown\n{}--- expected\n{}--- actual\n{}",
+            example.line, example.section, example.markdown, example.html, actual
+        );
+    }
+    assert!(
+        required.is_empty(),
+        "{} examples in required sections failed",
+        required.len()
+    );
+}
+
+#[test]
+fn normalizes_whitespace_between_tags() {
+    assert_eq!(
+        normalize("<ul>\n<li>a <em>b</em>\nc</li>\n</ul>\n<hr />\n"),
+        normalize("<ul><li>a <em>b</em> c</li></ul><hr>")
+    );
+    assert_eq!(
+        normalize("<pre><code>a\n  b\n</code></pre>"),
+        "<pre><code>a\n  b\n</code></pre>"
+    );
+    assert_ne!(
+        normalize("<em>a</em> <em>b</em>"),
+        normalize("<em>a</em><em>b</em>")
+    );
+}
@@ -0,0 +1,28 @@
+/// Writes a string as a JSON string literal.
+pub fn push_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings() {
+        let mut output = String::new();
+        push_string(&mut output, "a \"quote\" \\ é\n\u{1}");
+        assert_eq!(output, "\"a \\\"quote\\\" \\\\ é\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,556 @@
+use markdown::{
+    ast::{plain_text, Block, Document},
+    escape_html,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+};
+
+pub mod json;
+
+/// Terms shorter than this many characters aren't indexed.
+const MIN_TERM_LENGTH: usize = 2;
+
+/// How many times more a term in a heading or page title counts than one in
+/// the text.
+const HEADING_WEIGHT: usize = 3;
+
+/// Excerpts of sections are cut to about this many characters.
+const EXCERPT_LENGTH: usize = 160;
+
+/// BM25 parameters: how quickly more occurrences of a term stop mattering,
+/// and how much long sections are penalized.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// How much a term found as the prefix of a longer one counts, compared to
+/// finding the term itself.
+const PREFIX_WEIGHT: f64 = 0.5;
+
+/// Snippets show this many words before the first match, and this many in
+/// total.
+const SNIPPET_CONTEXT: usize = 8;
+const SNIPPET_WORDS: usize = 32;
+
+struct Page {
+    url: String,
+    title: String,
+}
+
+/// The part of a page from one heading to the next, which is what searches
+/// find, so results can link straight to the heading.
+struct Section {
+    page: usize,
+    /// The id of the heading, or `None` for the text before the first one.
+    anchor: Option<String>,
+    heading: String,
+    text: String,
+    /// The number of terms in the section, counting heading terms as many
+    /// times as they weigh.
+    length: usize,
+}
+
+/// A section a term is in, and how many times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Posting {
+    section: usize,
+    frequency: usize,
+}
+
+/// An inverted index of the text of every page, split into sections at the
+/// headings.
+#[derive(Default)]
+pub struct SearchIndex {
+    pages: Vec<Page>,
+    sections: Vec<Section>,
+    terms: BTreeMap<String, Vec<Posting>>,
+    /// The sum of the lengths of all sections.
+    total_length: usize,
+}
+
+/// A section found by a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub title: String,
+    pub url: String,
+    pub anchor: Option<String>,
+    pub heading: String,
+    /// HTML of the text around the first match, with matches in `<mark>`.
+    pub snippet: String,
+    pub score: f64,
+}
+
+impl SearchIndex {
+    /// Indexes the text of a document, which is found at `url`.
+    pub fn add_page(&mut self, url: &str, title: &str, document: &Document) {
+        let page = self.pages.len();
+        self.pages.push(Page {
+            url: url.to_string(),
+            title: title.to_string(),
+        });
+
+        let mut sections = vec![Section {
+            page,
+            anchor: None,
+            heading: title.to_string(),
+            text: String::new(),
+            length: 0,
+        }];
+        collect_sections(&document.blocks, &mut sections);
+        // A page starting with a heading has nothing before it, and the
+        // first heading stands in for the page title.
+        let starts_with_heading = sections.len() > 1 && sections[0].text.trim().is_empty();
+        if starts_with_heading {
+            sections.remove(0);
+        }
+
+        for (i, mut section) in sections.into_iter().enumerate() {
+            let mut frequencies: HashMap<String, usize> = HashMap::new();
+            for term in tokenize(&section.text) {
+                *frequencies.entry(term).or_default() += 1;
+            }
+            let mut headings = vec![section.heading.as_str()];
+            if i == 0 && starts_with_heading && section.heading != title {
+                headings.push(title);
+            }
+            for term in headings.into_iter().flat_map(tokenize) {
+                *frequencies.entry(term).or_default() += HEADING_WEIGHT;
+            }
+
+            section.length = frequencies.values().sum();
+            self.total_length += section.length;
+            let id = self.sections.len();
+            for (term, frequency) in frequencies {
+                self.terms.entry(term).or_default().push(Posting {
+                    section: id,
+                    frequency,
+                });
+            }
+            self.sections.push(section);
+        }
+    }
+
+    /// Finds the sections with all the terms of the query, best first, ranked
+    /// with BM25. The last term also matches as a prefix, so that queries
+    /// typed so far find something, like in the search UI.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Hit> {
+        let terms: Vec<String> = tokenize(query).collect();
+        let average_length = self.total_length as f64 / self.sections.len().max(1) as f64;
+
+        let mut scores: Option<HashMap<usize, f64>> = None;
+        for (i, term) in terms.iter().enumerate() {
+            let mut term_scores: HashMap<usize, f64> = HashMap::new();
+            for (matched, postings) in self.matches(term, is_prefix(&terms, i)) {
+                let weight = if matched == term { 1.0 } else { PREFIX_WEIGHT };
+                let frequency = postings.len() as f64;
+                let count = self.sections.len() as f64;
+                let idf = (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln();
+                for posting in postings {
+                    let tf = posting.frequency as f64;
+                    let length = self.sections[posting.section].length as f64;
+                    let norm = 1.0 - B + B * length / average_length.max(1.0);
+                    let score = idf * tf * (K1 + 1.0) / (tf + K1 * norm);
+                    *term_scores.entry(posting.section).or_default() += weight * score;
+                }
+            }
+
+            scores = Some(match scores {
+                None => term_scores,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(section, score)| {
+                        Some((section, score + term_scores.get(&section)?))
+                    })
+                    .collect(),
+            });
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores.unwrap_or_default().into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(id, score)| {
+                let section = &self.sections[id];
+                let page = &self.pages[section.page];
+                Hit {
+                    title: page.title.clone(),
+                    url: page.url.clone(),
+                    anchor: section.anchor.clone(),
+                    heading: section.heading.clone(),
+                    snippet: snippet(&section.text, &terms),
+                    score,
+                }
+            })
+            .collect()
+    }
+
+    /// The indexed terms equal to `term`, or starting with it for a prefix.
+    fn matches<'a>(
+        &'a self,
+        term: &'a str,
+        prefix: bool,
+    ) -> Box<dyn Iterator<Item = (&'a String, &'a Vec<Posting>)> + 'a> {
+        if prefix {
+            let range = self
+                .terms
+                .range::<str, _>((Bound::Included(term), Bound::Unbounded));
+            Box::new(range.take_while(move |(matched, _)| matched.starts_with(term)))
+        } else {
+            Box::new(self.terms.get_key_value(term).into_iter())
+        }
+    }
+
+    /// Writes the index as JSON for the search UI, in the form
+    ///
+    /// ```json
+    /// {
+    ///   "pages": [["url", "title"]],
+    ///   "sections": [[page, "anchor", "heading", "excerpt", length]],
+    ///   "terms": {"term": [document frequency, section, frequency, ...]}
+    /// }
+    /// ```
+    ///
+    /// where sections are referred to by their index, and the sections of a
+    /// term are followed by how often it is in each.
+    pub fn to_json(&self) -> String {
+        let mut output = String::from("{\"pages\":[");
+        for (i, page) in self.pages.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            output.push('[');
+            json::push_string(&mut output, &page.url);
+            output.push(',');
+            json::push_string(&mut output, &page.title);
+            output.push(']');
+        }
+
+        output.push_str("],\"sections\":[");
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            output.push_str(&format!("[{},", section.page));
+            json::push_string(&mut output, section.anchor.as_deref().unwrap_or_default());
+            output.push(',');
+            json::push_string(&mut output, &section.heading);
+            output.push(',');
+            json::push_string(&mut output, &excerpt(&section.text));
+            output.push_str(&format!(",{}]", section.length));
+        }
+
+        output.push_str("],\"terms\":{");
+        for (i, (term, postings)) in self.terms.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            json::push_string(&mut output, term);
+            output.push_str(&format!(":[{}", postings.len()));
+            for posting in postings {
+                output.push_str(&format!(",{},{}", posting.section, posting.frequency));
+            }
+            output.push(']');
+        }
+        output.push_str("}}\n");
+        output
+    }
+}
+
+/// Splits text into lowercase terms at everything that isn't a letter or a
+/// digit. The search UI splits queries the same way.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TERM_LENGTH)
+        .map(str::to_lowercase)
+}
+
+fn collect_sections(blocks: &[Block], sections: &mut Vec<Section>) {
+    for block in blocks {
+        match block {
+            Block::Heading { id, content, .. } => {
+                let page = sections[0].page;
+                sections.push(Section {
+                    page,
+                    anchor: id.clone(),
+                    heading: plain_text(content),
+                    text: String::new(),
+                    length: 0,
+                });
+            }
+            Block::Paragraph(content) => push_text(sections, &plain_text(content)),
+            Block::CodeBlock { code, .. } => push_text(sections, code),
+            Block::List(list) => {
+                for item in &list.items {
+                    collect_sections(item, sections);
+                }
+            }
+            Block::BlockQuote(blocks) => collect_sections(blocks, sections),
+            Block::Admonition { title, blocks, .. } => {
+                push_text(sections, title);
+                collect_sections(blocks, sections);
+            }
+            Block::Table(table) => {
+                for cell in table.header.iter().chain(table.rows.iter().flatten()) {
+                    push_text(sections, &plain_text(cell));
+                }
+            }
+            Block::Html(_) | Block::ThematicBreak | Block::TableOfContents(_) => (),
+        }
+    }
+}
+
+fn push_text(sections: &mut [Section], text: &str) {
+    if let Some(section) = sections.last_mut() {
+        if !section.text.is_empty() {
+            section.text.push('\n');
+        }
+        section.text.push_str(text.trim());
+    }
+}
+
+/// The start of a text with its whitespace collapsed, cut at a word.
+fn excerpt(text: &str) -> String {
+    let mut excerpt = String::new();
+    for word in text.split_whitespace() {
+        if excerpt.chars().count() + word.chars().count() > EXCERPT_LENGTH {
+            excerpt.push('…');
+            break;
+        }
+        if !excerpt.is_empty() {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+    }
+    excerpt
+}
+
+/// Whether the `i`th term of a query also matches as a prefix: only the
+/// last one does.
+fn is_prefix(terms: &[String], i: usize) -> bool {
+    i + 1 == terms.len()
+}
+
+/// Cuts the text around the first word matching a term, escaped as HTML,
+/// with every matching word in `<mark>`. Words match terms the way
+/// [`SearchIndex::search`] does.
+fn snippet(text: &str, terms: &[String]) -> String {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                words.push(s..i);
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    let is_match = |word: &str| {
+        let word = word.to_lowercase();
+        terms
+            .iter()
+            .enumerate()
+            .any(|(i, term)| match is_prefix(terms, i) {
+                true => word.starts_with(term.as_str()),
+                false => word == *term,
+            })
+    };
+
+    let first = words
+        .iter()
+        .position(|word| is_match(&text[word.clone()]))
+        .unwrap_or(0);
+    let from = first.saturating_sub(SNIPPET_CONTEXT);
+    let to = (from + SNIPPET_WORDS).min(words.len());
+    if from >= to {
+        return String::new();
+    }
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    let mut position = words[from].start;
+    for word in &words[from..to] {
+        for c in text[position..word.start].chars() {
+            if !c.is_whitespace() {
+                snippet.push_str(&escape_html(&c.to_string()));
+            } else if !snippet.ends_with(' ') {
+                snippet.push(' ');
+            }
+        }
+        let word_text = escape_html(&text[word.clone()]);
+        if is_match(&text[word.clone()]) {
+            snippet.push_str(&format!("<mark>{word_text}</mark>"));
+        } else {
+            snippet.push_str(&word_text);
+        }
+        position = word.end;
+    }
+    if to < words.len() {
+        snippet.push('…');
+    } else {
+        // Keep the punctuation ending the text.
+        snippet.push_str(&escape_html(text[position..].trim_end()));
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use markdown::parse;
+
+    fn postings(index: &SearchIndex, term: &str) -> Vec<(usize, usize)> {
+        index.terms[term]
+            .iter()
+            .map(|posting| (posting.section, posting.frequency))
+            .collect()
+    }
+
+    #[test]
+    fn tokens() {
+        let terms: Vec<String> =
+            tokenize("Run `cargo build --release` on x86_64, Ünïcode!").collect();
+        assert_eq!(
+            terms,
+            [
+                "run",
+                "cargo",
+                "build",
+                "release",
+                "on",
+                "x86",
+                "64",
+                "ünïcode"
+            ]
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let mut index = SearchIndex::default();
+        let intro = parse("Deploys go out daily.\n\n## Rollback\n\nRun `deploy rollback`.\n\n- ## Notes\n  Deploy carefully");
+        index.add_page("ops/deploy.html", "Deploy", &intro);
+        let heading_first = parse("# Setup\n\nInstall it.");
+        index.add_page("setup.html", "Getting started", &heading_first);
+
+        let sections: Vec<_> = index
+            .sections
+            .iter()
+            .map(|s| {
+                (
+                    s.page,
+                    s.anchor.as_deref(),
+                    s.heading.as_str(),
+                    s.text.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                (0, None, "Deploy", "Deploys go out daily."),
+                (0, Some("rollback"), "Rollback", "Run deploy rollback."),
+                (0, Some("notes"), "Notes", "Deploy carefully"),
+                (1, Some("setup"), "Setup", "Install it."),
+            ]
+        );
+
+        assert_eq!(postings(&index, "deploy"), [(0, 3), (1, 1), (2, 1)]);
+        assert_eq!(postings(&index, "rollback"), [(1, 4)]);
+        assert_eq!(postings(&index, "started"), [(3, 3)]);
+        assert_eq!(index.sections[1].length, 6);
+    }
+
+    #[test]
+    fn json() {
+        let mut index = SearchIndex::default();
+        index.add_page("a \"b\".html", "A\tB", &parse("Hi hi\n\n# Next"));
+        assert_eq!(
+            index.to_json(),
+            concat!(
+                "{\"pages\":[[\"a \\\"b\\\".html\",\"A\\tB\"]],",
+                "\"sections\":[[0,\"\",\"A\\tB\",\"Hi hi\",2],[0,\"next\",\"Next\",\"\",3]],",
+                "\"terms\":{\"hi\":[1,0,2],\"next\":[1,1,3]}}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn search() {
+        let mut index = SearchIndex::default();
+        index.add_page(
+            "deploy.html",
+            "Deploy",
+            &parse(
+                "# Deploy\n\nDeploy with `make deploy`.\n\n## Rollback\n\nRoll back a bad deploy.",
+            ),
+        );
+        index.add_page("setup.html", "Setup", &parse("Install, then deploy once."));
+
+        let found = |query| {
+            index
+                .search(query, 10)
+                .into_iter()
+                .map(|hit| (hit.url, hit.anchor))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            found("deploy"),
+            [
+                ("deploy.html".to_string(), Some("deploy".to_string())),
+                ("deploy.html".to_string(), Some("rollback".to_string())),
+                ("setup.html".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            found("bad depl"),
+            [("deploy.html".to_string(), Some("rollback".to_string()))]
+        );
+        assert_eq!(found("rollback install"), []);
+        assert_eq!(found("!"), []);
+
+        let hits = index.search("roll", 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].heading, "Rollback");
+        assert_eq!(hits[0].snippet, "<mark>Roll</mark> back a bad deploy.");
+        assert!(hits[0].score > 0.0);
+    }
+
+    #[test]
+    fn snippets() {
+        let terms = ["deploy".to_string(), "db".to_string()];
+        assert_eq!(
+            snippet("Run the <b> DB   migration, then deploy.", &terms),
+            "Run the &lt;b&gt; <mark>DB</mark> migration, then <mark>deploy</mark>."
+        );
+        // Only the last term matches as a prefix, as in search.
+        assert_eq!(
+            snippet("Deployment needs DBs and a deploy.", &terms),
+            "Deployment needs <mark>DBs</mark> and a <mark>deploy</mark>."
+        );
+
+        let long = format!("{} deploy {}", "before ".repeat(20), "after ".repeat(40));
+        let cut = snippet(&long, &terms);
+        assert!(cut.starts_with("…before"));
+        assert!(cut.ends_with("after…"));
+        assert_eq!(cut.matches("before").count(), SNIPPET_CONTEXT);
+        assert_eq!(cut.split_whitespace().count(), SNIPPET_WORDS);
+
+        assert_eq!(snippet("No match here", &terms), "No match here");
+        assert_eq!(snippet("", &terms), "");
+    }
+
+    #[test]
+    fn excerpts() {
+        assert_eq!(excerpt("a\n  b"), "a b");
+        let long = "word ".repeat(100);
+        let cut = excerpt(&long);
+        assert!(cut.ends_with("word…"));
+        assert!(cut.chars().count() <= EXCERPT_LENGTH + 1);
+    }
+}
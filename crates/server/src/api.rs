@@ -0,0 +1,51 @@
+use search::{json::push_string, SearchIndex};
+
+use crate::http::{Request, Response};
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+/// `GET /api/search?q=...&limit=...`: searches the pages and returns the hits
+/// as JSON, best first.
+pub fn search(request: &Request, index: &SearchIndex) -> Response {
+    let Some(query) = request.query_param("q") else {
+        return error(400, "missing `q` parameter");
+    };
+    let limit = match request.query_param("limit").map(str::parse::<usize>) {
+        None => DEFAULT_LIMIT,
+        Some(Ok(limit)) if limit > 0 => limit.min(MAX_LIMIT),
+        Some(_) => return error(400, "`limit` should be a positive number"),
+    };
+
+    let mut json = String::from("{\"query\":");
+    push_string(&mut json, query);
+    json.push_str(",\"hits\":[");
+    for (i, hit) in index.search(query, limit).iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str("{\"title\":");
+        push_string(&mut json, &hit.title);
+        json.push_str(",\"path\":");
+        push_string(&mut json, &format!("/{}", hit.url));
+        json.push_str(",\"anchor\":");
+        match &hit.anchor {
+            Some(anchor) => push_string(&mut json, anchor),
+            None => json.push_str("null"),
+        }
+        json.push_str(",\"heading\":");
+        push_string(&mut json, &hit.heading);
+        json.push_str(",\"snippet\":");
+        push_string(&mut json, &hit.snippet);
+        json.push_str(&format!(",\"score\":{:.4}}}", hit.score));
+    }
+    json.push_str("]}\n");
+    Response::json(200, json)
+}
+
+fn error(status: u16, message: &str) -> Response {
+    let mut json = String::from("{\"error\":");
+    push_string(&mut json, message);
+    json.push_str("}\n");
+    Response::json(status, json)
+}
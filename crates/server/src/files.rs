@@ -0,0 +1,229 @@
+use std::{
+    ffi::OsString,
+    fs::read,
+    path::{Path, PathBuf},
+};
+
+use crate::http::{percent_encode_path, Response};
+
+const NOT_FOUND_PAGE: &str = "404.html";
+
+/// Serves the file at `request_path` under `root`, which has to be
+/// canonical. Directories serve their `index.html`, and paths without an
+/// extension serve the `.html` file of the same name, so `/guides/setup`
+/// shows `guides/setup.html`. The `hidden` files, relative to the root, are
+/// not found.
+pub fn serve_file(root: &Path, hidden: &[PathBuf], request_path: &str) -> Response {
+    let Some(relative) = relative_path(request_path) else {
+        return Response::text(403, "Forbidden");
+    };
+
+    let mut path = root.join(&relative);
+    if path.is_dir() {
+        // Relative links in the index only work from a URL ending in `/`.
+        if !request_path.ends_with('/') {
+            let location = percent_encode_path(&format!("{request_path}/"));
+            return Response::text(301, "Moved permanently").with_header("Location", &location);
+        }
+        path = path.join("index.html");
+    } else if !path.is_file() && !request_path.ends_with('/') {
+        let mut html = OsString::from(path.as_os_str());
+        html.push(".html");
+        path = PathBuf::from(html);
+    }
+
+    // Symlinks could still point outside the root.
+    match path.canonicalize() {
+        Ok(canonical)
+            if canonical.starts_with(root)
+                && canonical.is_file()
+                && !hidden.iter().any(|file| canonical == root.join(file)) =>
+        {
+            match read(&canonical) {
+                Ok(body) => Response::new(200, content_type(&canonical), body),
+                Err(e) => {
+                    eprintln!("Failed to read {canonical:?}: {e}");
+                    Response::text(500, "Internal server error")
+                }
+            }
+        }
+        _ => not_found(root),
+    }
+}
+
+/// Turns a URL path into a path relative to the root, or `None` if it would
+/// leave the root.
+fn relative_path(request_path: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for part in request_path.split('/') {
+        match part {
+            "" | "." => (),
+            ".." => return None,
+            part if part.contains(['\\', '\0', ':']) => return None,
+            part => path.push(part),
+        }
+    }
+    Some(path)
+}
+
+/// The root's `404.html`, or a plain page if it has none.
+fn not_found(root: &Path) -> Response {
+    match read(root.join(NOT_FOUND_PAGE)) {
+        Ok(body) => Response::new(404, "text/html; charset=utf-8", body),
+        Err(_) => Response::new(
+            404,
+            "text/html; charset=utf-8",
+            "<!DOCTYPE html>\n<title>Not found</title>\n<h1>Not found</h1>\n<p>There is no page here.</p>\n",
+        ),
+    }
+}
+
+pub fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json; charset=utf-8",
+        "txt" => "text/plain; charset=utf-8",
+        "md" | "markdown" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml; charset=utf-8",
+        "yaml" | "yml" => "application/yaml; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    fn body(response: &Response) -> &str {
+        std::str::from_utf8(&response.body).unwrap()
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn serving() {
+        let dir = std::env::temp_dir().join(format!("wikiup-files-{}", std::process::id()));
+        create_dir_all(dir.join("out/guides/my docs")).unwrap();
+        write(dir.join("secret.txt"), "secret").unwrap();
+        write(dir.join("out/index.html"), "home").unwrap();
+        write(dir.join("out/guides/setup.html"), "setup").unwrap();
+        write(dir.join("out/guides/my docs/index.html"), "docs").unwrap();
+        write(dir.join("out/style.css"), "body {}").unwrap();
+        let root = dir.join("out").canonicalize().unwrap();
+
+        let response = serve_file(&root, &[], "/");
+        assert_eq!((response.status, body(&response)), (200, "home"));
+        assert_eq!(
+            header(&response, "Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+
+        let response = serve_file(&root, &[], "/guides/setup");
+        assert_eq!((response.status, body(&response)), (200, "setup"));
+        let response = serve_file(&root, &[], "/guides/setup.html");
+        assert_eq!((response.status, body(&response)), (200, "setup"));
+
+        let response = serve_file(&root, &[], "/style.css");
+        assert_eq!(
+            header(&response, "Content-Type"),
+            Some("text/css; charset=utf-8")
+        );
+
+        let response = serve_file(&root, &[], "/guides/my docs");
+        assert_eq!(response.status, 301);
+        assert_eq!(header(&response, "Location"), Some("/guides/my%20docs/"));
+        let response = serve_file(&root, &[], "/guides/my docs/");
+        assert_eq!((response.status, body(&response)), (200, "docs"));
+
+        assert_eq!(serve_file(&root, &[], "/missing").status, 404);
+        assert_eq!(serve_file(&root, &[], "/guides/setup/").status, 404);
+        assert_eq!(serve_file(&root, &[], "/../secret.txt").status, 403);
+        assert_eq!(
+            serve_file(&root, &[], "/guides/..\\..\\secret.txt").status,
+            403
+        );
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("out/link.txt")).unwrap();
+            assert_eq!(serve_file(&root, &[], "/link.txt").status, 404);
+        }
+
+        create_dir_all(dir.join("out/_wikiup")).unwrap();
+        write(dir.join("out/_wikiup/manifest.tsv"), "pages").unwrap();
+        let hidden = [PathBuf::from("_wikiup/manifest.tsv")];
+        assert_eq!(serve_file(&root, &[], "/_wikiup/manifest.tsv").status, 200);
+        assert_eq!(
+            serve_file(&root, &hidden, "/_wikiup/manifest.tsv").status,
+            404
+        );
+        assert_eq!(
+            serve_file(&root, &hidden, "/_wikiup//./manifest.tsv").status,
+            404
+        );
+
+        write(dir.join("out/404.html"), "custom").unwrap();
+        let response = serve_file(&root, &[], "/missing");
+        assert_eq!((response.status, body(&response)), (404, "custom"));
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path("/a/./b//c.html"),
+            Some(PathBuf::from("a/b/c.html"))
+        );
+        assert_eq!(relative_path("/"), Some(PathBuf::new()));
+        assert_eq!(relative_path("/a/../b"), None);
+        assert_eq!(relative_path("/C:/windows"), None);
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!(content_type(Path::new("a/b.PNG")), "image/png");
+        assert_eq!(
+            content_type(Path::new("search.js")),
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(
+            content_type(Path::new("Makefile")),
+            "application/octet-stream"
+        );
+    }
+}
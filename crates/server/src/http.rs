@@ -0,0 +1,316 @@
+use std::io::{self, BufRead, Read, Write};
+
+/// Requests with a longer request line or header are rejected.
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+
+const MAX_HEADERS: usize = 100;
+
+pub struct Request {
+    pub method: String,
+    /// The percent-decoded path, without the query.
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Returns the value of a header, whose name is matched ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the client wants to send more requests on this connection,
+    /// which HTTP/1.1 assumes unless told otherwise and HTTP/1.0 doesn't.
+    pub fn keep_alive(&self) -> bool {
+        let connection = self.header("Connection").unwrap_or_default();
+        let has = |token: &str| {
+            connection
+                .split(',')
+                .any(|value| value.trim().eq_ignore_ascii_case(token))
+        };
+        if self.version == "HTTP/1.0" {
+            has("keep-alive")
+        } else {
+            !has("close")
+        }
+    }
+
+    /// Whether a body follows the headers. Bodies aren't read, so the
+    /// connection can't be reused after such a request.
+    pub fn has_body(&self) -> bool {
+        self.header("Transfer-Encoding").is_some()
+            || self
+                .header("Content-Length")
+                .is_some_and(|length| length.trim() != "0")
+    }
+}
+
+/// Reads the request line and headers of a request. Returns `Ok(None)` if
+/// the connection was closed before a request started, and an
+/// `InvalidData` error for a malformed request.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let Some(request_line) = read_line(reader)? else {
+        return Ok(None);
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("malformed request line"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(invalid("unsupported HTTP version"));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = percent_decode(path, false).ok_or_else(|| invalid("malformed path"))?;
+    if !path.starts_with('/') {
+        return Err(invalid("path doesn't start with `/`"));
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?.ok_or_else(|| invalid("unexpected end of headers"))?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(invalid("too many headers"));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid("malformed header"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    Ok(Some(Request {
+        method: method.to_string(),
+        path,
+        query: parse_query(query),
+        version: version.to_string(),
+        headers,
+    }))
+}
+
+/// Reads a line without its `\r\n`, or `None` at the end of the stream.
+fn read_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = Read::take(reader, MAX_LINE_LENGTH as u64 + 1).read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return Err(invalid("line too long"));
+    }
+    line.pop();
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| invalid("request isn't UTF-8"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Parses `a=1&b=two+words`, percent-decoding names and values and skipping
+/// malformed pairs.
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((percent_decode(name, true)?, percent_decode(value, true)?))
+        })
+        .collect()
+}
+
+/// Decodes `%XX` escapes, and `+` as a space in query strings. Returns
+/// `None` for malformed escapes or text that isn't UTF-8.
+pub fn percent_decode(text: &str, plus_as_space: bool) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Escapes everything in a path except unreserved characters and `/`.
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), content_type.into())],
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, body: String) -> Self {
+        Self::new(status, "application/json; charset=utf-8", body)
+    }
+
+    pub fn text(status: u16, body: &str) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", format!("{body}\n"))
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Writes the response. The body is left out for `HEAD` requests, but
+    /// `Content-Length` still gives its length.
+    pub fn write_to(&self, writer: &mut impl Write, include_body: bool) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n\r\n", self.body.len()));
+
+        writer.write_all(head.as_bytes())?;
+        if include_body {
+            writer.write_all(&self.body)?;
+        }
+        writer.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        let mut input: &[u8] =
+            b"GET /api/search?q=deploy+now&limit=5 HTTP/1.1\r\nHost: wiki\r\nAccept:  */*\r\n\r\n";
+        let request = read_request(&mut input).unwrap().unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/api/search");
+        assert_eq!(request.query_param("q"), Some("deploy now"));
+        assert_eq!(request.query_param("limit"), Some("5"));
+        assert_eq!(request.header("accept"), Some("*/*"));
+        assert!(input.is_empty());
+        assert!(request.keep_alive());
+        assert!(!request.has_body());
+
+        let mut input: &[u8] =
+            b"POST / HTTP/1.0\r\nConnection: Keep-Alive\r\nContent-Length: 3\r\n\r\nabc";
+        let request = read_request(&mut input).unwrap().unwrap();
+        assert!(request.keep_alive());
+        assert!(request.has_body());
+        let mut input: &[u8] = b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n";
+        assert!(!read_request(&mut input).unwrap().unwrap().keep_alive());
+        let mut input: &[u8] = b"GET / HTTP/1.0\r\n\r\n";
+        assert!(!read_request(&mut input).unwrap().unwrap().keep_alive());
+
+        assert!(read_request(&mut &b""[..]).unwrap().is_none());
+        for malformed in [
+            &b"GET\r\n\r\n"[..],
+            b"GET / HTTP/2\r\n\r\n",
+            b"GET nope HTTP/1.1\r\n\r\n",
+            b"GET / HTTP/1.1\r\nno colon\r\n\r\n",
+            b"GET / HTTP/1.1\r\n",
+        ] {
+            let error = read_request(&mut &malformed[..]).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn decoding() {
+        assert_eq!(
+            percent_decode("/My%20Page.html", false).as_deref(),
+            Some("/My Page.html")
+        );
+        assert_eq!(percent_decode("a+b", false).as_deref(), Some("a+b"));
+        assert_eq!(percent_decode("%C3%A9", true).as_deref(), Some("é"));
+        assert_eq!(percent_decode("%zz", true), None);
+        assert_eq!(percent_decode("%2", true), None);
+        assert_eq!(percent_encode_path("/My Page/é?"), "/My%20Page/%C3%A9%3F");
+        assert_eq!(
+            parse_query("q=a%26b&&flag&bad=%ff"),
+            [("q".into(), "a&b".into()), ("flag".into(), "".into())]
+        );
+    }
+
+    #[test]
+    fn responses() {
+        let mut output = Vec::new();
+        let response = Response::text(404, "Not found").with_header("Connection", "close");
+        response.write_to(&mut output, false).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                "HTTP/1.1 404 Not Found\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "Connection: close\r\n",
+                "Content-Length: 10\r\n\r\n",
+            )
+        );
+    }
+}
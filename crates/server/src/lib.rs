@@ -1,16 +1,68 @@
-use std::net::TcpListener;
-use util::threads::maybe_spawn;
+use search::SearchIndex;
+use std::{
+    io::{BufReader, ErrorKind},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    thread::spawn,
+    time::Duration,
+};
+use util::log::{is_quiet, is_verbose};
+
+use http::{read_request, Request, Response};
+use live_reload::{inject_script, EVENTS_PATH};
+
+mod api;
+mod files;
+pub mod http;
+mod live_reload;
+
+pub use live_reload::LiveReload;
+
+/// How long an idle keep-alive connection is held open.
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct ServerConfig {
     pub addr: String,
     pub port: u16,
+    /// The directory whose files are served.
+    pub root: PathBuf,
+    /// The index `/api/search` searches, which is replaced after rebuilds.
+    pub search_index: Arc<RwLock<SearchIndex>>,
+    /// Pages reload when this is told to, if set.
+    pub live_reload: Option<Arc<LiveReload>>,
+    /// Files under the root that are never served, relative to it.
+    pub hidden: Vec<PathBuf>,
 }
 
-pub fn dummy_server(config: ServerConfig) -> std::io::Result<()> {
+struct Site {
+    root: PathBuf,
+    hidden: Vec<PathBuf>,
+    search_index: Arc<RwLock<SearchIndex>>,
+    live_reload: Option<Arc<LiveReload>>,
+}
+
+pub fn serve(config: ServerConfig) -> std::io::Result<()> {
+    let site = Arc::new(Site {
+        root: config.root.canonicalize()?,
+        hidden: config.hidden,
+        search_index: config.search_index,
+        live_reload: config.live_reload,
+    });
+
     let addr = format!("{}:{}", config.addr, config.port);
     let listener = TcpListener::bind(&addr)?;
-    println!("Listening on http://{addr}");
+    if !is_quiet() {
+        println!("Listening on http://{addr}");
+    }
+
+    accept(listener, site);
+    Ok(())
+}
 
+/// Handles every connection on its own thread, so idle keep-alive
+/// connections and event streams never hold up the others.
+fn accept(listener: TcpListener, site: Arc<Site>) {
     for stream in listener.incoming() {
         let stream = match stream {
             Ok(stream) => stream,
@@ -20,17 +72,132 @@ pub fn dummy_server(config: ServerConfig) -> std::io::Result<()> {
             }
         };
 
-        maybe_spawn(move || {
-            match stream.peer_addr() {
-                Ok(s) => {
-                    println!("Got connection from {s}");
+        let site = site.clone();
+        spawn(move || handle_connection(stream, &site));
+    }
+}
+
+fn handle_connection(stream: TcpStream, site: &Site) {
+    if let Err(e) = stream.set_read_timeout(Some(KEEP_ALIVE_TIMEOUT)) {
+        eprintln!("Failed to set connection timeout: {e}");
+        return;
+    }
+    let mut reader = match stream.try_clone() {
+        Ok(stream) => BufReader::new(stream),
+        Err(e) => {
+            eprintln!("Failed to clone connection: {e}");
+            return;
+        }
+    };
+    let mut writer = stream;
+
+    loop {
+        let (response, include_body, keep_alive) = match read_request(&mut reader) {
+            Ok(Some(request)) => {
+                let is_events = request.method == "GET" && request.path == EVENTS_PATH;
+                if let (true, Some(live_reload)) = (is_events, &site.live_reload) {
+                    // The stream stays open until the client goes away.
+                    live_reload.stream_events(writer);
+                    return;
                 }
-                Err(e) => {
-                    eprintln!("Failed to get peer address: {}", e);
+                let response = route(&request, site);
+                if is_verbose() {
+                    println!("{} {} {}", request.method, request.path, response.status);
                 }
-            };
-        });
+                (
+                    response,
+                    request.method != "HEAD",
+                    request.keep_alive() && !request.has_body(),
+                )
+            }
+            Ok(None) => return,
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                (Response::text(400, "Bad request"), true, false)
+            }
+            // The client went quiet.
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return,
+            Err(e) => {
+                eprintln!("Failed to read request: {e}");
+                return;
+            }
+        };
+
+        let connection = if keep_alive { "keep-alive" } else { "close" };
+        let response = response.with_header("Connection", connection);
+        if let Err(e) = response.write_to(&mut writer, include_body) {
+            eprintln!("Failed to write response: {e}");
+            return;
+        }
+        if !keep_alive {
+            return;
+        }
     }
+}
 
-    Ok(())
+fn route(request: &Request, site: &Site) -> Response {
+    if request.method != "GET" && request.method != "HEAD" {
+        return Response::text(405, "Method not allowed").with_header("Allow", "GET, HEAD");
+    }
+
+    match request.path.as_str() {
+        "/api/search" => match site.search_index.read() {
+            Ok(search_index) => api::search(request, &search_index),
+            Err(_) => Response::text(500, "Internal server error"),
+        },
+        path => {
+            let mut response = files::serve_file(&site.root, &site.hidden, path);
+            let is_html = response
+                .headers
+                .iter()
+                .any(|(name, value)| name == "Content-Type" && value.starts_with("text/html"));
+            if is_html && site.live_reload.is_some() {
+                inject_script(&mut response.body);
+            }
+            response.with_header("Cache-Control", "no-cache")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        fs::{create_dir_all, remove_dir_all, write},
+        io::{Read, Write},
+    };
+
+    #[test]
+    fn idle_connections() {
+        let dir = std::env::temp_dir().join(format!("wikiup-server-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        write(dir.join("index.html"), "home").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let site = Arc::new(Site {
+            root: dir.canonicalize().unwrap(),
+            hidden: Vec::new(),
+            search_index: Arc::new(RwLock::new(SearchIndex::default())),
+            live_reload: None,
+        });
+        spawn(move || accept(listener, site));
+
+        // Connections that never send anything, more than there are threads.
+        let idle: Vec<TcpStream> = (0..32).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        // Less than the keep-alive timeout, so this fails if the idle
+        // connections hold up this one.
+        stream
+            .set_read_timeout(Some(KEEP_ALIVE_TIMEOUT / 5))
+            .unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("home"), "{response}");
+
+        drop(idle);
+        remove_dir_all(&dir).unwrap();
+    }
 }
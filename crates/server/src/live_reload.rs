@@ -0,0 +1,98 @@
+use std::{
+    io::Write,
+    net::TcpStream,
+    sync::{Condvar, Mutex},
+    time::Duration,
+};
+
+/// The Server-Sent Events stream pages listen on.
+pub const EVENTS_PATH: &str = "/_wikiup/live-reload";
+
+/// A comment is sent this often so that closed connections are noticed.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+const SCRIPT: &str = "<script>new EventSource(\"/_wikiup/live-reload\").onmessage = () => location.reload();</script>\n";
+
+/// Tells open pages to reload after a rebuild.
+#[derive(Default)]
+pub struct LiveReload {
+    version: Mutex<u64>,
+    changed: Condvar,
+}
+
+impl LiveReload {
+    pub fn reload(&self) {
+        if let Ok(mut version) = self.version.lock() {
+            *version += 1;
+            self.changed.notify_all();
+        }
+    }
+
+    /// Sends a `reload` event after every rebuild until the client goes away.
+    pub(crate) fn stream_events(&self, mut writer: TcpStream) {
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+        if writer.write_all(head.as_bytes()).is_err() {
+            return;
+        }
+
+        let Ok(mut version) = self.version.lock() else {
+            return;
+        };
+        let mut seen = *version;
+        loop {
+            // A rebuild may have happened while the last event was written.
+            if *version == seen {
+                version = match self.changed.wait_timeout(version, HEARTBEAT_INTERVAL) {
+                    Ok((version, _)) => version,
+                    Err(_) => return,
+                };
+            }
+            let event = if *version == seen {
+                ": heartbeat\n\n"
+            } else {
+                seen = *version;
+                "data: reload\n\n"
+            };
+            // Don't hold the lock while writing to a slow client.
+            drop(version);
+            if writer
+                .write_all(event.as_bytes())
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                return;
+            }
+            version = match self.version.lock() {
+                Ok(version) => version,
+                Err(_) => return,
+            };
+        }
+    }
+}
+
+/// Adds the script that listens for reloads to an HTML page.
+pub(crate) fn inject_script(body: &mut Vec<u8>) {
+    let position = body
+        .windows(7)
+        .rposition(|window| window.eq_ignore_ascii_case(b"</body>"))
+        .unwrap_or(body.len());
+    body.splice(position..position, SCRIPT.bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn injection() {
+        let mut body = b"<html><body><p>hi</p></BODY></html>".to_vec();
+        inject_script(&mut body);
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("<html><body><p>hi</p><script>"));
+        assert!(body.ends_with("</script>\n</BODY></html>"));
+
+        let mut body = b"<p>fragment</p>".to_vec();
+        inject_script(&mut body);
+        assert!(body.ends_with(SCRIPT.as_bytes()));
+    }
+}
@@ -0,0 +1,147 @@
+/// Matches pattern segments against path segments, where a `**` segment
+/// matches any number of path segments.
+pub(crate) fn match_segments<P: AsRef<str>, S: AsRef<str>>(pattern: &[P], segments: &[S]) -> bool {
+    match pattern.split_first() {
+        None => segments.is_empty(),
+        Some((first, rest)) if first.as_ref() == "**" => {
+            (0..=segments.len()).any(|skip| match_segments(rest, &segments[skip..]))
+        }
+        Some((first, rest)) => match segments.split_first() {
+            Some((segment, segments)) => {
+                match_segment(first.as_ref(), segment.as_ref()) && match_segments(rest, segments)
+            }
+            None => false,
+        },
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Char(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `[a-z]`, or `[!a-z]` when negated.
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Char(expected) => *expected == c,
+            Token::Any => true,
+            Token::Star => false,
+            Token::Class { ranges, negated } => {
+                ranges.iter().any(|&(from, to)| (from..=to).contains(&c)) != *negated
+            }
+        }
+    }
+}
+
+/// Splits a segment pattern into tokens. A `\` makes the next character
+/// literal, and a `[` without a closing `]` is literal too.
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let token = match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                Token::Char(chars[i])
+            }
+            '?' => Token::Any,
+            '*' => Token::Star,
+            '[' => match parse_class(&chars[i + 1..]) {
+                Some((token, length)) => {
+                    i += length;
+                    token
+                }
+                None => Token::Char('['),
+            },
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    tokens
+}
+
+/// Parses a class after its `[`, returning it and how many characters it
+/// took including the `]`.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+    // A `]` right at the start is part of the class.
+    while i < chars.len() && (chars[i] != ']' || i == usize::from(negated)) {
+        let from = chars[i];
+        match chars.get(i + 1..i + 3) {
+            Some(&['-', to]) if to != ']' => {
+                ranges.push((from, to));
+                i += 3;
+            }
+            _ => {
+                ranges.push((from, from));
+                i += 1;
+            }
+        }
+    }
+    (i < chars.len()).then_some((Token::Class { ranges, negated }, i + 1))
+}
+
+/// Matches one segment, where `*` is any run of characters, `?` is any one
+/// character and `[...]` is one character of a class.
+pub(crate) fn match_segment(pattern: &str, text: &str) -> bool {
+    let pattern = tokenize(pattern);
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*` if the rest doesn't match.
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(Token::Star) => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(token) if token.matches(text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|token| *token == Token::Star)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments() {
+        assert!(match_segment("*.md", "setup.md"));
+        assert!(match_segment("s?tup*", "setup.md"));
+        assert!(match_segment("*", ""));
+        assert!(!match_segment("*.md", "setup.html"));
+        assert!(!match_segment("setup", "setup.md"));
+        assert!(match_segment("[sS]etup.md", "Setup.md"));
+        assert!(match_segment("v[0-9].md", "v1.md"));
+        assert!(!match_segment("v[!0-9].md", "v1.md"));
+        assert!(match_segment("[]x]", "]"));
+        assert!(match_segment("\\*.md", "*.md"));
+        assert!(!match_segment("\\*.md", "a.md"));
+        assert!(match_segment("[a", "[a"));
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(OFFSET_BASIS)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Values that feed [`Fnv1a`] the same bytes in every build, on every
+/// platform, so their hashes can be saved and compared between runs.
+/// `std::hash::Hash` makes no such promise: `usize` differs in width and the
+/// encoding of `str` is up to the standard library.
+pub trait StableHash {
+    fn stable_hash(&self, hasher: &mut Fnv1a);
+}
+
+/// Hashes a value with [`Fnv1a`].
+pub fn fnv1a<T: StableHash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = Fnv1a::default();
+    value.stable_hash(&mut hasher);
+    hasher.finish()
+}
+
+impl StableHash for u64 {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl StableHash for i64 {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl StableHash for usize {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        (*self as u64).stable_hash(hasher);
+    }
+}
+
+impl StableHash for bool {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&[u8::from(*self)]);
+    }
+}
+
+/// Bytes go after their length, so `("ab", "c")` and `("a", "bc")` differ.
+impl StableHash for [u8] {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.len().stable_hash(hasher);
+        hasher.write(self);
+    }
+}
+
+impl StableHash for str {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.as_bytes().stable_hash(hasher);
+    }
+}
+
+impl StableHash for String {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.as_str().stable_hash(hasher);
+    }
+}
+
+impl StableHash for Path {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.as_os_str().as_encoded_bytes().stable_hash(hasher);
+    }
+}
+
+impl StableHash for PathBuf {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.as_path().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        match self {
+            Some(value) => {
+                true.stable_hash(hasher);
+                value.stable_hash(hasher);
+            }
+            None => false.stable_hash(hasher),
+        }
+    }
+}
+
+impl<T: StableHash> StableHash for [T] {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.len().stable_hash(hasher);
+        for item in self {
+            item.stable_hash(hasher);
+        }
+    }
+}
+
+impl<T: StableHash, const N: usize> StableHash for [T; N] {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.as_slice().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash> StableHash for Vec<T> {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.as_slice().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &T {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        (**self).stable_hash(hasher);
+    }
+}
+
+macro_rules! tuple {
+    ($($name:ident)+) => {
+        impl<$($name: StableHash),+> StableHash for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn stable_hash(&self, hasher: &mut Fnv1a) {
+                let ($($name,)+) = self;
+                $($name.stable_hash(hasher);)+
+            }
+        }
+    };
+}
+
+tuple!(A B);
+tuple!(A B C);
+tuple!(A B C D);
+tuple!(A B C D E);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_hashes() {
+        let bytes = |bytes: &[u8]| {
+            let mut hasher = Fnv1a::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(bytes(b""), 0xcbf29ce484222325);
+        assert_eq!(bytes(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(bytes(b"foobar"), 0x85944171f73967e8);
+        // The length as 8 little-endian bytes, then the bytes.
+        assert_eq!(fnv1a("a"), bytes(b"\x01\0\0\0\0\0\0\0a"));
+        assert_ne!(fnv1a(&("ab", "c")), fnv1a(&("a", "bc")));
+        assert_ne!(fnv1a(&Some(0u64)), fnv1a(&None::<u64>));
+    }
+}
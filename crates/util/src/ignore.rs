@@ -0,0 +1,205 @@
+use crate::glob::{match_segment, match_segments};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// One line of an ignore file.
+#[derive(Debug, PartialEq)]
+struct Rule {
+    segments: Vec<String>,
+    /// Patterns with a `/` other than a trailing one match from the
+    /// directory of their file, others match a name at any depth below it.
+    anchored: bool,
+    /// `!pattern`, which includes what earlier rules ignored.
+    negated: bool,
+    /// `pattern/`, which only matches directories.
+    dir_only: bool,
+}
+
+impl Rule {
+    /// Parses a line in gitignore syntax, or returns `None` for blank lines
+    /// and comments.
+    fn parse(line: &str) -> Option<Self> {
+        let mut line = line.trim_end_matches('\r');
+        // Trailing spaces don't count unless escaped.
+        while line.ends_with(' ') && !line.ends_with("\\ ") {
+            line = &line[..line.len() - 1];
+        }
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (line, negated) = match line.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        // `\#` and `\!` start patterns with those characters.
+        let line = line
+            .strip_prefix('\\')
+            .filter(|rest| rest.starts_with(['#', '!']))
+            .unwrap_or(line);
+        let (line, dir_only) = match line.strip_suffix('/') {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        let anchored = line.contains('/');
+        let segments: Vec<String> = line
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+        if segments.is_empty() {
+            return None;
+        }
+        Some(Rule {
+            segments,
+            anchored,
+            negated,
+            dir_only,
+        })
+    }
+
+    /// Whether the rule matches a path relative to the directory of its file.
+    fn matches(&self, segments: &[Cow<str>], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if !self.anchored {
+            return segments
+                .last()
+                .is_some_and(|name| match_segment(&self.segments[0], name));
+        }
+        // A trailing `**` matches everything inside, but not the directory
+        // itself.
+        if self.segments.last().is_some_and(|last| last == "**")
+            && segments.len() < self.segments.len()
+        {
+            return false;
+        }
+        match_segments(&self.segments, segments)
+    }
+}
+
+/// Ignore rules in gitignore syntax for one directory, on top of the rules of
+/// the directories above it. Later rules win over earlier ones, and rules of
+/// a directory win over its parents'.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    /// The directory the rules are relative to, relative to the root.
+    base: PathBuf,
+    rules: Vec<Rule>,
+    parent: Option<Arc<IgnoreRules>>,
+}
+
+impl IgnoreRules {
+    /// Parses the lines of an ignore file, or other patterns, in `base`.
+    pub fn new<'a>(
+        base: &Path,
+        lines: impl IntoIterator<Item = &'a str>,
+        parent: Option<Arc<IgnoreRules>>,
+    ) -> Self {
+        IgnoreRules {
+            base: base.to_path_buf(),
+            rules: lines.into_iter().filter_map(Rule::parse).collect(),
+            parent,
+        }
+    }
+
+    /// Whether there are no rules besides the parent's.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether a path relative to the root is ignored. Only the path itself
+    /// is checked, since anything in an ignored directory is never reached.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.matched(path, is_dir).unwrap_or(false)
+    }
+
+    /// Whether the last rule matching the path ignores it, or `None` if no
+    /// rule matches.
+    fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        if let Ok(relative) = path.strip_prefix(&self.base) {
+            let segments: Vec<Cow<str>> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            if let Some(rule) = self
+                .rules
+                .iter()
+                .rev()
+                .find(|rule| rule.matches(&segments, is_dir))
+            {
+                return Some(!rule.negated);
+            }
+        }
+        self.parent.as_ref()?.matched(path, is_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        assert_eq!(Rule::parse(""), None);
+        assert_eq!(Rule::parse("# comment"), None);
+        assert_eq!(Rule::parse("/"), None);
+        assert_eq!(
+            Rule::parse("!build/  "),
+            Some(Rule {
+                segments: vec!["build".into()],
+                anchored: false,
+                negated: true,
+                dir_only: true,
+            })
+        );
+        assert_eq!(
+            Rule::parse("/docs/*.tmp"),
+            Some(Rule {
+                segments: vec!["docs".into(), "*.tmp".into()],
+                anchored: true,
+                negated: false,
+                dir_only: false,
+            })
+        );
+        assert_eq!(Rule::parse("\\#notes").unwrap().segments, ["#notes"]);
+        assert!(!Rule::parse("\\!important").unwrap().negated);
+    }
+
+    #[test]
+    fn matching() {
+        let root = Arc::new(IgnoreRules::new(
+            Path::new(""),
+            "target/\n*.log\n!keep.log\n/drafts\nguides/**/old\nnotes/**\n!notes/index.md".lines(),
+            None,
+        ));
+        let ignored = |path: &str, is_dir| root.is_ignored(Path::new(path), is_dir);
+        assert!(ignored("target", true));
+        assert!(ignored("a/target", true));
+        assert!(!ignored("target", false));
+        assert!(ignored("a/b.log", false));
+        assert!(!ignored("a/keep.log", false));
+        assert!(ignored("drafts", true));
+        assert!(!ignored("a/drafts", true));
+        assert!(ignored("guides/old", true));
+        assert!(ignored("guides/x/y/old", false));
+        assert!(!ignored("notes", true));
+        assert!(ignored("notes/todo.md", false));
+        assert!(!ignored("notes/index.md", false));
+        assert!(!ignored("index.md", false));
+
+        let nested = IgnoreRules::new(Path::new("a"), ["!*.log", "/local"], Some(root.clone()));
+        assert!(!nested.is_ignored(Path::new("a/b.log"), false));
+        assert!(nested.is_ignored(Path::new("c/b.log"), false));
+        assert!(nested.is_ignored(Path::new("a/local"), false));
+        assert!(!nested.is_ignored(Path::new("local"), false));
+        assert!(nested.is_ignored(Path::new("a/target"), true));
+        assert!(!nested.is_empty());
+        assert!(IgnoreRules::new(Path::new(""), ["# nothing"], None).is_empty());
+    }
+}
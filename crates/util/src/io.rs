@@ -1,30 +1,27 @@
 use std::{
-    fs::read_dir,
-    path::PathBuf,
+    fmt,
+    fs::{metadata, read_dir, read_to_string, Metadata},
+    io::ErrorKind,
+    path::{Path, PathBuf},
     sync::{
-        mpsc::{channel, Receiver},
-        Arc, Mutex,
+        mpsc::{channel, Receiver, Sender},
+        Arc, Condvar, Mutex,
     },
 };
 
-use crate::threads::{get_max_threads, maybe_spawn};
+use crate::{
+    ignore::IgnoreRules,
+    threads::{get_max_threads, maybe_spawn},
+};
 
-pub fn get_all_markdown_files(root: PathBuf) -> Receiver<PathBuf> {
-    let files_rx = get_all_files(root);
+pub fn get_all_markdown_files(root: PathBuf, options: WalkOptions) -> Receiver<PathBuf> {
+    let files_rx = get_all_files(root, options);
     let (tx, rx) = channel();
 
     maybe_spawn(move || {
         while let Ok(path) = files_rx.recv() {
-            let is_markdown = path
-                .extension()
-                .map(|ext| ext == "md" || ext == "markdown")
-                .unwrap_or(false);
-
-            if is_markdown {
-                if let Err(err) = tx.send(path) {
-                    eprintln!("Failed to send path: {err}");
-                    break;
-                }
+            if is_markdown(&path) && tx.send(path).is_err() {
+                break;
             }
         }
     });
@@ -32,61 +29,551 @@ pub fn get_all_markdown_files(root: PathBuf) -> Receiver<PathBuf> {
     rx
 }
 
-pub fn get_all_files(root: PathBuf) -> Receiver<PathBuf> {
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext == "md" || ext == "markdown")
+        .unwrap_or(false)
+}
+
+/// What [`walk`] leaves out. The default lists every file, following symbolic
+/// links that stay inside the root.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Skip files and directories whose names start with a `.`.
+    pub skip_hidden: bool,
+    /// Files with gitignore-syntax patterns, such as `.gitignore`, read in
+    /// every directory and applying to everything in it.
+    pub ignore_files: Vec<String>,
+    /// Files with gitignore-syntax patterns read only in the root.
+    pub root_ignore_files: Vec<String>,
+    /// More gitignore-syntax patterns, relative to the root, which win over
+    /// the root's ignore files.
+    pub patterns: Vec<String>,
+    pub symlinks: SymlinkPolicy,
+    /// How many directories deep to go below the root. `Some(0)` only lists
+    /// the files in the root.
+    pub max_depth: Option<usize>,
+}
+
+/// What a walk does with symbolic links.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Follow every link.
+    Follow,
+    /// Leave every link out.
+    Skip,
+    /// Follow links to files and directories inside the root, and leave out
+    /// the rest.
+    #[default]
+    WithinRoot,
+}
+
+impl SymlinkPolicy {
+    pub const NAMES: &[&str] = &["follow", "skip", "within-root"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "follow" => Some(SymlinkPolicy::Follow),
+            "skip" => Some(SymlinkPolicy::Skip),
+            "within-root" => Some(SymlinkPolicy::WithinRoot),
+            _ => None,
+        }
+    }
+}
+
+/// A file or directory a walk left out for something other than the ignore
+/// rules, which is worth telling the user about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// A link left out by [`SymlinkPolicy::Skip`].
+    Symlink,
+    /// A link left out by [`SymlinkPolicy::WithinRoot`].
+    OutsideRoot,
+    /// A link to a directory it is in, which would be walked forever.
+    Loop,
+    /// A directory deeper than [`WalkOptions::max_depth`].
+    TooDeep,
+    /// A directory, link or ignore file that couldn't be read.
+    Error(String),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Symlink => write!(f, "symbolic link"),
+            SkipReason::OutsideRoot => write!(f, "link to outside the root"),
+            SkipReason::Loop => write!(f, "link to a directory it is in"),
+            SkipReason::TooDeep => write!(f, "deeper than the maximum depth"),
+            SkipReason::Error(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+pub enum WalkEntry {
+    File(PathBuf),
+    /// A directory that was read, the root included.
+    Dir(PathBuf),
+    Skipped(Skipped),
+}
+
+/// Identifies a directory however it was reached, to find link loops.
+#[cfg(unix)]
+type DirId = (u64, u64);
+
+#[cfg(unix)]
+fn dir_id(_path: &Path, metadata: &Metadata) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path, _metadata: &Metadata) -> Option<DirId> {
+    path.canonicalize().ok()
+}
+
+/// A directory to read, with the ignore rules of its parent and the
+/// directories it is in, itself included.
+struct DirToWalk {
+    path: PathBuf,
+    parent_rules: Option<Arc<IgnoreRules>>,
+    ancestors: Vec<DirId>,
+}
+
+/// The directories a walk has left to read, shared by its threads.
+#[derive(Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct QueueState {
+    dirs: Vec<DirToWalk>,
+    /// Directories being read, which can add more.
+    in_progress: usize,
+}
+
+impl Queue {
+    /// Takes a directory to read, waiting while there are none but others
+    /// are still being read. Returns `None` once everything has been read.
+    fn pop(&self) -> Option<Reading<'_>> {
+        let mut state = self.state.lock().ok()?;
+        loop {
+            if let Some(dir) = state.dirs.pop() {
+                state.in_progress += 1;
+                return Some(Reading { queue: self, dir });
+            }
+            if state.in_progress == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).ok()?;
+        }
+    }
+
+    fn push(&self, dir: DirToWalk) {
+        if let Ok(mut state) = self.state.lock() {
+            state.dirs.push(dir);
+            self.changed.notify_one();
+        }
+    }
+}
+
+/// A directory taken from the queue, which counts as being read until this
+/// is dropped, however reading it ends.
+struct Reading<'a> {
+    queue: &'a Queue,
+    dir: DirToWalk,
+}
+
+impl Drop for Reading<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.queue.state.lock() {
+            state.in_progress -= 1;
+            if state.in_progress == 0 && state.dirs.is_empty() {
+                self.queue.changed.notify_all();
+            }
+        }
+    }
+}
+
+/// Sends every file under `root` that `options` doesn't leave out, and what
+/// was skipped on the way. Ignored directories are never read, and neither
+/// are links to a directory they are in.
+pub fn walk(root: PathBuf, options: WalkOptions) -> Receiver<WalkEntry> {
     let (tx, rx) = channel();
-    let to_walk = Arc::new(Mutex::new(vec![root]));
+    let options = Arc::new(options);
+    let canonical_root = Arc::new(root.canonicalize().ok());
+    let ancestors = metadata(&root)
+        .ok()
+        .and_then(|metadata| dir_id(&root, &metadata))
+        .into_iter()
+        .collect();
+    let queue = Arc::new(Queue::default());
+    queue.push(DirToWalk {
+        path: root.clone(),
+        parent_rules: None,
+        ancestors,
+    });
 
     let max_threads = get_max_threads();
     for _ in 0..max_threads {
-        let to_walk = to_walk.clone();
+        let queue = queue.clone();
         let tx = tx.clone();
+        let root = root.clone();
+        let canonical_root = canonical_root.clone();
+        let options = options.clone();
 
         maybe_spawn(move || loop {
-            let mut p: Option<PathBuf> = None;
-            {
-                if let Ok(mut to_walk) = to_walk.lock() {
-                    if let Some(hmm) = to_walk.pop() {
-                        p = Some(hmm);
-                    }
-                }
-            }
-
-            let path_to_walk = match p {
-                Some(p) => p,
-                None => break,
+            let Some(reading) = queue.pop() else {
+                break;
             };
+            let dir_to_walk = &reading.dir;
+            let skip =
+                |path: PathBuf, reason| send(&tx, WalkEntry::Skipped(Skipped { path, reason }));
+            let rules = dir_rules(
+                &root,
+                &dir_to_walk.path,
+                &options,
+                dir_to_walk.parent_rules.clone(),
+                skip,
+            );
 
-            let dir = match read_dir(path_to_walk) {
+            let dir = match read_dir(&dir_to_walk.path) {
                 Ok(dir) => dir,
                 Err(err) => {
-                    eprintln!("Failed to read dir: {err}");
+                    if !skip(dir_to_walk.path.clone(), SkipReason::Error(err.to_string())) {
+                        break;
+                    }
                     continue;
                 }
             };
+            if !send(&tx, WalkEntry::Dir(dir_to_walk.path.clone())) {
+                break;
+            }
 
             for entry in dir {
                 let entry = match entry {
                     Ok(entry) => entry,
                     Err(err) => {
-                        eprintln!("Failed to read entry: {err}");
+                        if !skip(dir_to_walk.path.clone(), SkipReason::Error(err.to_string())) {
+                            break;
+                        }
                         continue;
                     }
                 };
 
+                if options.skip_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
                 let new_path = entry.path();
-                if new_path.is_dir() {
+                let is_link = entry
+                    .file_type()
+                    .is_ok_and(|file_type| file_type.is_symlink());
+                let entry_metadata = match is_link {
+                    true => metadata(&new_path),
+                    false => entry.metadata(),
+                };
+                let is_dir = entry_metadata.as_ref().is_ok_and(Metadata::is_dir);
+                if let (Some(rules), Ok(relative)) = (&rules, new_path.strip_prefix(&root)) {
+                    if rules.is_ignored(relative, is_dir) {
+                        continue;
+                    }
+                }
+
+                let reason = match (&entry_metadata, options.symlinks) {
+                    (_, SymlinkPolicy::Skip) if is_link => Some(SkipReason::Symlink),
+                    (Err(err), _) => Some(SkipReason::Error(err.to_string())),
+                    (Ok(_), SymlinkPolicy::WithinRoot)
+                        if is_link && !is_within(&new_path, &canonical_root) =>
                     {
-                        if let Ok(mut to_walk) = to_walk.lock() {
-                            to_walk.push(new_path);
+                        Some(SkipReason::OutsideRoot)
+                    }
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    if !skip(new_path, reason) {
+                        break;
+                    }
+                    continue;
+                }
+
+                if !is_dir {
+                    if !send(&tx, WalkEntry::File(new_path)) {
+                        break;
+                    }
+                    continue;
+                }
+
+                let depth = dir_to_walk.ancestors.len();
+                if options.max_depth.is_some_and(|max_depth| depth > max_depth) {
+                    if !skip(new_path, SkipReason::TooDeep) {
+                        break;
+                    }
+                    continue;
+                }
+                let mut ancestors = dir_to_walk.ancestors.clone();
+                if let Some(id) = entry_metadata
+                    .ok()
+                    .and_then(|metadata| dir_id(&new_path, &metadata))
+                {
+                    if ancestors.contains(&id) {
+                        if !skip(new_path, SkipReason::Loop) {
+                            break;
                         }
+                        continue;
                     }
-                } else if let Err(err) = tx.send(new_path) {
-                    eprintln!("Failed to send path: {err}");
-                    break;
+                    ancestors.push(id);
                 }
+                queue.push(DirToWalk {
+                    path: new_path,
+                    parent_rules: rules.clone(),
+                    ancestors,
+                });
             }
         });
     }
 
     rx
 }
+
+/// Sends an entry of a walk, returning false when no one is listening
+/// anymore and the walk can stop.
+fn send(tx: &Sender<WalkEntry>, entry: WalkEntry) -> bool {
+    tx.send(entry).is_ok()
+}
+
+/// Whether a link leads inside the root, once every link on the way is
+/// followed.
+fn is_within(path: &Path, canonical_root: &Option<PathBuf>) -> bool {
+    match (path.canonicalize(), canonical_root) {
+        (Ok(target), Some(root)) => target.starts_with(root),
+        _ => false,
+    }
+}
+
+/// Sends every file under `root` that `options` doesn't leave out, dropping
+/// what [`walk`] reports as skipped.
+pub fn get_all_files(root: PathBuf, options: WalkOptions) -> Receiver<PathBuf> {
+    let entries_rx = walk(root, options);
+    let (tx, rx) = channel();
+
+    maybe_spawn(move || {
+        while let Ok(entry) = entries_rx.recv() {
+            if let WalkEntry::File(path) = entry {
+                if tx.send(path).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    rx
+}
+
+/// Every file under `root` that `options` doesn't leave out, and what was
+/// skipped, both sorted. The walk is as parallel as [`walk`], but the result
+/// is the same every run.
+pub fn get_sorted_files(root: PathBuf, options: WalkOptions) -> (Vec<PathBuf>, Vec<Skipped>) {
+    let (mut files, mut skipped) = (Vec::new(), Vec::new());
+    for entry in walk(root, options) {
+        match entry {
+            WalkEntry::File(path) => files.push(path),
+            WalkEntry::Dir(_) => (),
+            WalkEntry::Skipped(entry) => skipped.push(entry),
+        }
+    }
+    files.sort();
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    (files, skipped)
+}
+
+/// The ignore rules for what's in `dir`: its parent's, with those of its
+/// ignore files, and in the root the patterns from the options, on top.
+/// Ignore files that can't be read are skipped.
+fn dir_rules(
+    root: &Path,
+    dir: &Path,
+    options: &WalkOptions,
+    parent: Option<Arc<IgnoreRules>>,
+    skip: impl Fn(PathBuf, SkipReason) -> bool,
+) -> Option<Arc<IgnoreRules>> {
+    let is_root = dir == root;
+    let mut names = options.ignore_files.iter().collect::<Vec<_>>();
+    if is_root {
+        names.extend(&options.root_ignore_files);
+    }
+
+    let mut lines = String::new();
+    for name in names {
+        let path = dir.join(name);
+        match read_to_string(&path) {
+            Ok(text) => {
+                lines.push_str(&text);
+                lines.push('\n');
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => (),
+            Err(err) => {
+                skip(path, SkipReason::Error(err.to_string()));
+            }
+        }
+    }
+    let patterns = options.patterns.iter().filter(|_| is_root);
+    let lines = lines.lines().chain(patterns.map(String::as_str));
+
+    let relative = dir.strip_prefix(root).unwrap_or(Path::new(""));
+    let rules = IgnoreRules::new(relative, lines, parent.clone());
+    match rules.is_empty() {
+        true => parent,
+        false => Some(Arc::new(rules)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::set_max_threads;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    #[test]
+    fn walking() {
+        let dir = std::env::temp_dir().join(format!("wikiup-walk-{}", std::process::id()));
+        for path in [
+            "b.md",
+            "a/z.md",
+            "a/b/c.md",
+            "a/local/x.md",
+            "a-b.md",
+            ".git/config",
+            "node_modules/pkg/readme.md",
+            "drafts/idea.md",
+            "drafts/ready.md",
+            "debug.log",
+        ] {
+            create_dir_all(dir.join(path).parent().unwrap()).unwrap();
+            write(dir.join(path), "").unwrap();
+        }
+        write(dir.join(".gitignore"), "node_modules/\n").unwrap();
+        write(dir.join("a/.gitignore"), "/local\n").unwrap();
+        write(dir.join(".wikiupignore"), "*.log\n").unwrap();
+        set_max_threads(4);
+
+        let files = |options| -> Vec<String> {
+            get_sorted_files(dir.clone(), options)
+                .0
+                .iter()
+                .map(|path| {
+                    path.strip_prefix(&dir)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect()
+        };
+        assert_eq!(files(WalkOptions::default()).len(), 13);
+        assert_eq!(
+            files(WalkOptions {
+                skip_hidden: true,
+                ignore_files: vec![".gitignore".into()],
+                root_ignore_files: vec![".wikiupignore".into()],
+                patterns: vec!["drafts/*".into(), "!ready.md".into()],
+                ..Default::default()
+            }),
+            ["a/b/c.md", "a/z.md", "a-b.md", "b.md", "drafts/ready.md"]
+        );
+
+        // An ignore file that can't be read is reported, not just left out.
+        create_dir_all(dir.join("a/b/.gitignore")).unwrap();
+        let (_, skipped) = get_sorted_files(
+            dir.clone(),
+            WalkOptions {
+                skip_hidden: true,
+                ignore_files: vec![".gitignore".into()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, dir.join("a/b/.gitignore"));
+        assert!(matches!(skipped[0].reason, SkipReason::Error(_)));
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let base = std::env::temp_dir().join(format!("wikiup-links-{}", std::process::id()));
+        let (dir, outside) = (base.join("root"), base.join("outside"));
+        for path in [
+            dir.join("a/b/c/deep.md"),
+            dir.join("index.md"),
+            outside.join("x.md"),
+        ] {
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, "").unwrap();
+        }
+        symlink(&dir, dir.join("a/loop")).unwrap();
+        symlink(dir.join("a/b"), dir.join("b")).unwrap();
+        symlink(&outside, dir.join("outside")).unwrap();
+        symlink(dir.join("missing.md"), dir.join("broken.md")).unwrap();
+        set_max_threads(4);
+
+        let walk = |symlinks, max_depth| -> (Vec<String>, Vec<String>) {
+            let relative = |path: &PathBuf| path.strip_prefix(&dir).unwrap().display().to_string();
+            let (files, skipped) = get_sorted_files(
+                dir.clone(),
+                WalkOptions {
+                    symlinks,
+                    max_depth,
+                    ..Default::default()
+                },
+            );
+            (
+                files.iter().map(relative).collect(),
+                skipped
+                    .iter()
+                    .map(|skipped| format!("{} {}", relative(&skipped.path), skipped.reason))
+                    .collect(),
+            )
+        };
+
+        let (files, skipped) = walk(SymlinkPolicy::WithinRoot, None);
+        assert_eq!(files, ["a/b/c/deep.md", "b/c/deep.md", "index.md"]);
+        assert_eq!(skipped.len(), 3);
+        assert!(skipped[0].starts_with("a/loop link to a directory"));
+        assert!(skipped[1].starts_with("broken.md "));
+        assert_eq!(skipped[2], "outside link to outside the root");
+
+        let (files, skipped) = walk(SymlinkPolicy::Follow, None);
+        assert_eq!(
+            files,
+            ["a/b/c/deep.md", "b/c/deep.md", "index.md", "outside/x.md"]
+        );
+        assert_eq!(skipped.len(), 2);
+
+        let (files, skipped) = walk(SymlinkPolicy::Skip, Some(1));
+        assert_eq!(files, ["index.md"]);
+        assert_eq!(
+            skipped,
+            [
+                "a/b deeper than the maximum depth",
+                "a/loop symbolic link",
+                "b symbolic link",
+                "broken.md symbolic link",
+                "outside symbolic link",
+            ]
+        );
+
+        remove_dir_all(&base).unwrap();
+    }
+}
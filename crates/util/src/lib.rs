@@ -1,2 +1,6 @@
+pub mod glob;
+pub mod hash;
+pub mod ignore;
 pub mod io;
+pub mod log;
 pub mod threads;
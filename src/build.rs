@@ -0,0 +1,772 @@
+use crate::config::{read_config, Config, CONFIG_FILE};
+use crate::layout::{
+    breadcrumbs, format_date, navigation_tree, page_context, page_title, render_navigation,
+    Layouts, NavItem, NavPage, SiteInfo, Template, LAYOUTS_DIR, SEARCH_INDEX_PATH, THEME_FILES,
+};
+use crate::manifest::{Manifest, PageEntry, MANIFEST_PATH};
+use crate::wiki::{relative_url, PageIndex};
+use markdown::{
+    ast::Document, parse_front_matter, parse_with_extensions, render_html, strip_front_matter,
+    table_of_contents, Extensions, Metadata, Options, Rendered,
+};
+use search::SearchIndex;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, copy, create_dir_all, read_to_string, remove_dir, remove_file, write},
+    io::ErrorKind,
+    path::{absolute, Path, PathBuf},
+    time::SystemTime,
+};
+use util::{
+    hash::fnv1a,
+    io::{get_sorted_files, is_markdown, Skipped, WalkOptions},
+    log::{is_quiet, is_verbose},
+};
+
+/// The root's ignore file, in gitignore syntax, for files to leave out of
+/// the wiki but not of git.
+const IGNORE_FILE: &str = ".wikiupignore";
+
+/// Where files of the theme directory go in the output, next to the
+/// built-in theme's.
+const THEME_OUT_DIR: &str = "_wikiup";
+
+/// Everything read from the root, kept around so single pages can be
+/// rebuilt when they change.
+pub struct Site {
+    root: PathBuf,
+    out: PathBuf,
+    config: Config,
+    /// The output directory relative to the root, when it is inside it.
+    out_in_root: Option<PathBuf>,
+    sources: BTreeMap<PathBuf, Source>,
+    assets: Vec<PathBuf>,
+    pages: PageIndex,
+    layouts: Layouts,
+    navigation: Vec<NavItem>,
+    /// The title and document of every page written, for the search index.
+    built: BTreeMap<PathBuf, (String, Document)>,
+    /// Pages, assets and layouts that failed to read, render or write.
+    failed: BTreeSet<PathBuf>,
+    /// What the pages in the output were rendered from, and which files the
+    /// build wrote there.
+    manifest: Manifest,
+    /// The files the previous build wrote, which are removed once a build no
+    /// longer writes them.
+    previous_outputs: BTreeSet<PathBuf>,
+    /// How many pages the last build skipped because nothing they depend on
+    /// changed.
+    unchanged: usize,
+    /// How many stale files the last build removed.
+    removed: usize,
+    /// Links and directories the walk left out other than by the ignore
+    /// rules, relative to the root.
+    skipped: Vec<Skipped>,
+}
+
+struct Source {
+    metadata: Metadata,
+    document: Document,
+    title: String,
+    last_modified: Option<SystemTime>,
+    /// A hash of the markdown and its last modified date.
+    hash: u64,
+}
+
+/// A page rendered through its layout.
+struct Page {
+    html: String,
+    /// Targets of `[[Page]]` links and the pages they resolve to.
+    links: Vec<(String, Option<PathBuf>)>,
+    /// Whether the front matter asks for a layout that doesn't exist.
+    missing_layout: bool,
+}
+
+impl Site {
+    /// Finds and reads the pages, assets and layouts under `root`.
+    pub fn load(root: &Path, out: &Path, config: Config) -> Self {
+        let manifest = Manifest::read(out);
+        let mut site = Site {
+            root: root.to_path_buf(),
+            out: out.to_path_buf(),
+            out_in_root: out_in_root(root, out),
+            layouts: Layouts::load(root, config.theme.as_deref()),
+            config,
+            sources: BTreeMap::new(),
+            assets: Vec::new(),
+            pages: PageIndex::new(&[]),
+            navigation: Vec::new(),
+            built: BTreeMap::new(),
+            failed: BTreeSet::new(),
+            previous_outputs: manifest.outputs(),
+            manifest,
+            unchanged: 0,
+            removed: 0,
+            skipped: Vec::new(),
+        };
+        site.failed.extend(site.layouts.invalid().iter().cloned());
+
+        // Sorted, so pages are read, and assets copied, in the same order every
+        // build.
+        let mut paths = Vec::new();
+        let (files, skipped) = get_sorted_files(root.to_path_buf(), site.walk_options());
+        site.add_skipped(skipped);
+        for path in files {
+            let path = match path.strip_prefix(root) {
+                Ok(path) => path.to_path_buf(),
+                Err(_) => {
+                    eprintln!("Failed to strip prefix from {path:?}");
+                    continue;
+                }
+            };
+
+            if site.is_template(&path) {
+                continue;
+            }
+            if is_markdown(&path) {
+                paths.push(path);
+            } else {
+                site.assets.push(path);
+            }
+        }
+
+        for path in paths {
+            match read_source(root, &path, &site.config) {
+                Ok(Some(source)) => {
+                    site.sources.insert(path, source);
+                }
+                Ok(None) => (),
+                Err(()) => {
+                    site.failed.insert(path);
+                }
+            }
+        }
+
+        let paths: Vec<PathBuf> = site.sources.keys().cloned().collect();
+        site.pages = PageIndex::new(&paths);
+        for (path, source) in &site.sources {
+            for alias in &source.metadata.aliases {
+                site.pages.add_alias(alias, path);
+            }
+        }
+        site.navigation = site.navigation_tree();
+
+        // Pages rendered with other settings or navigation can't be reused.
+        let site_hash = site.site_hash();
+        if site.manifest.site != site_hash {
+            site.manifest.site = site_hash;
+            site.manifest.pages.clear();
+        }
+        site
+    }
+
+    /// Writes the theme, every page that changed since the last build, the
+    /// search index and the assets, removes the files the last build wrote
+    /// that are no longer part of the site, and returns the search index.
+    pub fn build(&mut self) -> SearchIndex {
+        self.manifest.assets.clear();
+        for (theme_path, contents) in THEME_FILES {
+            let out_path = self.out.join(theme_path);
+            if create_parent_dir(&out_path) {
+                if let Err(err) = write(&out_path, contents) {
+                    eprintln!("Failed to write to {out_path:?}: {err}");
+                }
+            }
+        }
+        self.copy_theme_files();
+
+        self.unchanged = 0;
+        let paths: Vec<PathBuf> = self.sources.keys().cloned().collect();
+        for path in &paths {
+            if self.is_unchanged(path) {
+                self.unchanged += 1;
+                let source = &self.sources[path];
+                self.built.insert(
+                    path.clone(),
+                    (source.title.clone(), source.document.clone()),
+                );
+            } else {
+                self.build_page(path);
+            }
+        }
+        let search_index = self.write_search_index();
+
+        for path in &self.assets {
+            match copy_asset(&self.root.join(path), &self.out.join(path)) {
+                true => self.manifest.assets.insert(path.clone()),
+                false => self.failed.insert(path.clone()),
+            };
+        }
+
+        self.write_manifest();
+        self.prune();
+        search_index
+    }
+
+    /// Brings the output up to date with `changed` paths, relative to the
+    /// root. Edits to existing pages that keep their aliases only rebuild
+    /// those pages, or every page if the navigation changes; anything else
+    /// that could affect other pages (new, removed or renamed pages, aliases,
+    /// layouts, the theme, the config and ignore files) reloads and rebuilds
+    /// the whole site. Returns the new search index and how many pages were
+    /// rebuilt.
+    pub fn rebuild(&mut self, changed: &[PathBuf]) -> (SearchIndex, usize) {
+        let mut pages = Vec::new();
+        let mut full = false;
+        let mut navigation_changed = false;
+        for path in changed {
+            if self.is_settings_file(path) {
+                // An invalid config keeps the last good one.
+                if path == Path::new(CONFIG_FILE) {
+                    if let Some(config) = read_config(&self.root) {
+                        self.config = config;
+                    }
+                }
+                full = true;
+            } else if self.is_template(path) {
+                full = true;
+            } else if is_markdown(path) {
+                // Deleted pages have nothing to read.
+                let source = match self.root.join(path).is_file() {
+                    true => read_source(&self.root, path, &self.config),
+                    false => Ok(None),
+                };
+                match (self.sources.get(path), source) {
+                    (Some(old), Ok(Some(new))) if old.metadata.aliases == new.metadata.aliases => {
+                        navigation_changed |=
+                            old.title != new.title || old.metadata.order != new.metadata.order;
+                        self.sources.insert(path.clone(), new);
+                        pages.push(path.clone());
+                    }
+                    (None, Ok(None)) => {
+                        self.failed.remove(path);
+                    }
+                    (None, Err(())) => {
+                        self.failed.insert(path.clone());
+                    }
+                    _ => full = true,
+                }
+            } else if self.root.join(path).is_file() {
+                if let Err(i) = self.assets.binary_search(path) {
+                    self.assets.insert(i, path.clone());
+                }
+                if copy_asset(&self.root.join(path), &self.out.join(path)) {
+                    self.failed.remove(path);
+                    self.manifest.assets.insert(path.clone());
+                } else {
+                    self.failed.insert(path.clone());
+                }
+            } else {
+                // Removed assets are pruned from the output below.
+                self.assets.retain(|asset| asset != path);
+                self.manifest.assets.remove(path);
+                self.failed.remove(path);
+            }
+        }
+
+        if full {
+            let (root, out) = (self.root.clone(), self.out.clone());
+            *self = Site::load(&root, &out, std::mem::take(&mut self.config));
+            let search_index = self.build();
+            return (search_index, self.sources.len() - self.unchanged);
+        }
+
+        if navigation_changed {
+            self.navigation = self.navigation_tree();
+            self.manifest.site = self.site_hash();
+            pages = self.sources.keys().cloned().collect();
+        }
+        for path in &pages {
+            self.build_page(path);
+        }
+        self.write_manifest();
+        self.prune();
+        (self.write_search_index(), pages.len())
+    }
+
+    /// Renders every page without writing anything, printing files that
+    /// failed to load, broken wiki links and missing layouts. Returns how
+    /// many problems there were.
+    pub fn check(&self) -> usize {
+        let mut problems = self.failed.len();
+        for (path, source) in &self.sources {
+            let page = self.render_page(path, source);
+            // Targets with a `#` that don't resolve as a whole are tried again
+            // without the heading, and only that attempt counts.
+            let broken = page
+                .links
+                .iter()
+                .filter(|(target, page)| page.is_none() && !target.contains('#'));
+            for (target, _) in broken {
+                eprintln!("Broken link to [[{target}]] in {path:?}");
+                problems += 1;
+            }
+            problems += usize::from(page.missing_layout);
+        }
+        problems
+    }
+
+    /// How to find the files of the wiki under the root. Hidden files,
+    /// anything `.gitignore` files, `.wikiupignore` or the config ignore, the
+    /// config file and the output directory are left out.
+    pub fn walk_options(&self) -> WalkOptions {
+        let mut patterns = self.config.ignore.clone();
+        patterns.push(format!("/{CONFIG_FILE}"));
+        if let Some(out) = &self.out_in_root {
+            let out = out.to_string_lossy().replace('\\', "/");
+            patterns.push(format!("/{}/", escape_glob(&out)));
+        }
+        WalkOptions {
+            skip_hidden: true,
+            ignore_files: vec![".gitignore".to_string()],
+            root_ignore_files: vec![IGNORE_FILE.to_string()],
+            patterns,
+            symlinks: self.config.symlinks,
+            max_depth: self.config.max_depth,
+        }
+    }
+
+    /// Keeps what a walk skipped to report, relative to the root.
+    fn add_skipped(&mut self, skipped: Vec<Skipped>) {
+        for mut entry in skipped {
+            if let Ok(path) = entry.path.strip_prefix(&self.root) {
+                entry.path = path.to_path_buf();
+            }
+            self.skipped.push(entry);
+        }
+    }
+
+    /// The config and ignore files that apply in `dir`, relative to the root,
+    /// which decide what is in the wiki without being part of it.
+    pub fn settings_files(&self, dir: &Path) -> Vec<PathBuf> {
+        let options = self.walk_options();
+        let mut names = options.ignore_files;
+        if dir.as_os_str().is_empty() {
+            names.extend(options.root_ignore_files);
+            names.push(CONFIG_FILE.to_string());
+        }
+        names.iter().map(|name| dir.join(name)).collect()
+    }
+
+    pub fn is_settings_file(&self, path: &Path) -> bool {
+        path.parent()
+            .is_some_and(|dir| self.settings_files(dir).iter().any(|file| file == path))
+    }
+
+    /// Whether a path is part of the layouts or theme rather than the pages.
+    fn is_template(&self, path: &Path) -> bool {
+        path.starts_with(LAYOUTS_DIR)
+            || self
+                .config
+                .theme
+                .as_ref()
+                .is_some_and(|theme| path.starts_with(theme))
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn asset_count(&self) -> usize {
+        self.assets.len()
+    }
+
+    /// How many pages the last build left as they were.
+    pub fn unchanged_count(&self) -> usize {
+        self.unchanged
+    }
+
+    /// How many stale files the last build removed.
+    pub fn removed_count(&self) -> usize {
+        self.removed
+    }
+
+    /// Links and directories left out of the wiki for something other than
+    /// the ignore rules, such as a link loop.
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+
+    /// How many files failed to load or build.
+    pub fn failures(&self) -> usize {
+        self.failed.len()
+    }
+
+    fn navigation_tree(&self) -> Vec<NavItem> {
+        let pages: Vec<NavPage> = self
+            .sources
+            .iter()
+            .map(|(path, source)| NavPage {
+                path,
+                title: &source.title,
+                order: source.metadata.order,
+            })
+            .collect();
+        navigation_tree(&pages, &self.config.navigation)
+    }
+
+    /// Copies the files of the theme directory other than layouts into the
+    /// output, over the built-in theme's files of the same name.
+    fn copy_theme_files(&mut self) {
+        let Some(theme) = self.config.theme.clone() else {
+            return;
+        };
+        let theme_dir = self.root.join(&theme);
+        if !theme_dir.is_dir() {
+            eprintln!("Theme directory {theme_dir:?} doesn't exist");
+            self.failed.insert(theme);
+            return;
+        }
+
+        let options = WalkOptions {
+            skip_hidden: true,
+            symlinks: self.config.symlinks,
+            max_depth: self.config.max_depth,
+            ..Default::default()
+        };
+        let (files, skipped) = get_sorted_files(theme_dir.clone(), options);
+        self.add_skipped(skipped);
+        for path in files {
+            let Ok(relative) = path.strip_prefix(&theme_dir) else {
+                continue;
+            };
+            if relative.extension().is_some_and(|ext| ext == "html") {
+                continue;
+            }
+            let out_path = Path::new(THEME_OUT_DIR).join(relative);
+            if !copy_asset(&path, &self.out.join(&out_path)) {
+                self.failed.insert(theme.join(relative));
+            } else if !THEME_FILES
+                .iter()
+                .any(|(file, _)| out_path == Path::new(file))
+            {
+                // The built-in theme's files are always written, never pruned.
+                self.manifest.assets.insert(out_path);
+            }
+        }
+    }
+
+    fn build_page(&mut self, path: &Path) {
+        let Some(source) = self.sources.get(path) else {
+            return;
+        };
+        let page = self.render_page(path, source);
+        self.built.insert(
+            path.to_path_buf(),
+            (source.title.clone(), source.document.clone()),
+        );
+        let entry = PageEntry {
+            source: source.hash,
+            template: fnv1a(self.layout(path, &source.metadata, false).0),
+            output: path.with_extension("html"),
+            links: page.links,
+        };
+        self.manifest.pages.remove(path);
+
+        let out_path = self.out.join(path).with_extension("html");
+
+        if !create_parent_dir(&out_path) {
+            self.failed.insert(path.to_path_buf());
+            return;
+        }
+
+        match write(&out_path, page.html) {
+            Ok(_) => {
+                self.failed.remove(path);
+                self.manifest.pages.insert(path.to_path_buf(), entry);
+                if is_verbose() {
+                    println!("Wrote {out_path:?}");
+                }
+            }
+            Err(_) => {
+                eprintln!("Failed to write to {out_path:?}");
+                self.failed.insert(path.to_path_buf());
+            }
+        }
+    }
+
+    /// Whether the page's output is still what rendering it would write: its
+    /// markdown, layout and the pages its wiki links resolve to are the same
+    /// as in the last build.
+    fn is_unchanged(&self, path: &Path) -> bool {
+        let (Some(entry), Some(source)) = (self.manifest.pages.get(path), self.sources.get(path))
+        else {
+            return false;
+        };
+        let (layout, missing_layout) = self.layout(path, &source.metadata, false);
+        // Pages with a missing layout are rebuilt every time, to keep
+        // reporting it.
+        !missing_layout
+            && entry.source == source.hash
+            && entry.template == fnv1a(layout)
+            && self.out.join(&entry.output).is_file()
+            && entry
+                .links
+                .iter()
+                .all(|(target, resolved)| self.pages.resolve(target) == resolved.as_deref())
+    }
+
+    /// A hash of what every page depends on besides its own markdown and
+    /// layout.
+    fn site_hash(&self) -> u64 {
+        fnv1a(&(
+            env!("CARGO_PKG_VERSION"),
+            &self.config.title,
+            &self.config.base_url,
+            extension_flags(self.config.extensions),
+            &self.navigation,
+        ))
+    }
+
+    /// The layout a page goes through, and whether it asks for one that
+    /// doesn't exist, which is reported if `report` is set.
+    fn layout(&self, path: &Path, metadata: &Metadata, report: bool) -> (&Template, bool) {
+        match &metadata.layout {
+            Some(name) => match self.layouts.named(name) {
+                Some(layout) => (layout, false),
+                None => {
+                    if report {
+                        eprintln!(
+                            "No layout {name:?} in {LAYOUTS_DIR} for {path:?}, using the default"
+                        );
+                    }
+                    (self.layouts.page(), true)
+                }
+            },
+            None => (self.layouts.page(), false),
+        }
+    }
+
+    fn render_page(&self, path: &Path, source: &Source) -> Page {
+        let links = RefCell::new(Vec::new());
+        let resolve_wiki_link = |page: &str| {
+            let target = self.pages.resolve(page);
+            links
+                .borrow_mut()
+                .push((page.to_string(), target.map(Path::to_path_buf)));
+            target.map(|target| relative_url(path, target))
+        };
+        let options = Options {
+            wiki_link_resolver: Some(&resolve_wiki_link),
+            heading_anchors: true,
+            ..Default::default()
+        };
+        let rendered = Rendered {
+            html: render_html(&source.document, &options),
+            toc: table_of_contents(&source.document),
+            metadata: source.metadata.clone(),
+            document: Document::default(),
+        };
+
+        let (layout, missing_layout) = self.layout(path, &source.metadata, true);
+        let site = SiteInfo {
+            title: self.config.title.clone(),
+            base_url: self.config.base_url.clone(),
+        };
+        let breadcrumbs = breadcrumbs(path, &source.title, |page| self.sources.contains_key(page));
+        let navigation = render_navigation(&self.navigation, path);
+        let context = page_context(
+            path,
+            &rendered,
+            &site,
+            breadcrumbs,
+            navigation,
+            source.last_modified,
+        );
+
+        let mut links = links.into_inner();
+        links.sort();
+        links.dedup();
+        Page {
+            html: layout.render(&context),
+            links,
+            missing_layout,
+        }
+    }
+
+    /// Removes the files the previous build wrote that this one didn't,
+    /// leaving anything else in the output directory alone.
+    fn prune(&mut self) {
+        let outputs = self.manifest.outputs();
+        self.removed = 0;
+        for path in self.previous_outputs.difference(&outputs) {
+            if remove_output(&self.out, path) {
+                self.removed += 1;
+            }
+        }
+        self.previous_outputs = outputs;
+    }
+
+    fn write_manifest(&self) {
+        let manifest_path = self.out.join(MANIFEST_PATH);
+        if !create_parent_dir(&manifest_path) {
+            return;
+        }
+        if let Err(err) = write(&manifest_path, self.manifest.to_text()) {
+            eprintln!("Failed to write to {manifest_path:?}: {err}");
+        }
+    }
+
+    fn write_search_index(&self) -> SearchIndex {
+        let mut search_index = SearchIndex::default();
+        for (path, (title, document)) in &self.built {
+            search_index.add_page(&relative_url(Path::new(""), path), title, document);
+        }
+
+        let search_index_path = self.out.join(SEARCH_INDEX_PATH);
+        if let Err(err) = write(&search_index_path, search_index.to_json()) {
+            eprintln!("Failed to write to {search_index_path:?}: {err}");
+        }
+        search_index
+    }
+}
+
+/// Reads and parses a page, or returns `None` if it is a draft. Errors have
+/// already been printed when this returns one.
+fn read_source(root: &Path, path: &Path, config: &Config) -> Result<Option<Source>, ()> {
+    let markdown = match read_to_string(root.join(path)) {
+        Ok(markdown) => markdown,
+        Err(err) => {
+            eprintln!("Failed to read {path:?}: {err}");
+            return Err(());
+        }
+    };
+
+    match parse_front_matter(&markdown) {
+        Ok(metadata) if metadata.draft => {
+            if !is_quiet() {
+                println!("Skipped draft {path:?}");
+            }
+            Ok(None)
+        }
+        Ok(metadata) => {
+            let document = parse_with_extensions(strip_front_matter(&markdown), config.extensions);
+            let title = page_title(path, &metadata, &table_of_contents(&document));
+            let last_modified = fs::metadata(root.join(path))
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            // The date is part of the page, so touching a file rebuilds it.
+            let hash = fnv1a(&(&markdown, last_modified.map(format_date)));
+            Ok(Some(Source {
+                metadata,
+                document,
+                title,
+                last_modified,
+                hash,
+            }))
+        }
+        Err(err) => {
+            eprintln!("Invalid front matter in {path:?}, {err}");
+            Err(())
+        }
+    }
+}
+
+/// The extensions as flags, in a fixed order, for hashing.
+fn extension_flags(extensions: Extensions) -> [bool; 4] {
+    [
+        extensions.tables,
+        extensions.admonitions,
+        extensions.wiki_links,
+        extensions.toc,
+    ]
+}
+
+/// The output directory relative to the root, if it is inside the root. The
+/// output directory doesn't have to exist yet.
+fn out_in_root(root: &Path, out: &Path) -> Option<PathBuf> {
+    let root = root.canonicalize().ok()?;
+    let out = absolute(out).ok()?;
+    // Resolve the part that exists, so symlinks and `..` don't get in the way.
+    let mut existing = out.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+    let mut out = existing.canonicalize().ok()?;
+    out.extend(missing.iter().rev());
+
+    let relative = out.strip_prefix(&root).ok()?;
+    // Building into the root itself leaves nothing to skip.
+    (!relative.as_os_str().is_empty()).then(|| relative.to_path_buf())
+}
+
+/// Removes a file from the output and the directories it leaves empty,
+/// returning whether there was a file to remove.
+fn remove_output(out: &Path, path: &Path) -> bool {
+    let out_path = out.join(path);
+    match remove_file(&out_path) {
+        Ok(_) => {
+            if is_verbose() {
+                println!("Removed {out_path:?}");
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => return false,
+        Err(err) => {
+            eprintln!("Failed to remove {out_path:?}: {err}");
+            return false;
+        }
+    }
+
+    let mut dir = path.parent();
+    while let Some(parent) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        // Fails, and stops, at the first directory that isn't empty.
+        if remove_dir(out.join(parent)).is_err() {
+            break;
+        }
+        dir = parent.parent();
+    }
+    true
+}
+
+/// Copies a file, returning whether that worked.
+fn copy_asset(from: &Path, to: &Path) -> bool {
+    if !create_parent_dir(to) {
+        return false;
+    }
+
+    match copy(from, to) {
+        Ok(_) => {
+            if is_verbose() {
+                println!("Copied {to:?}");
+            }
+            true
+        }
+        Err(_) => {
+            eprintln!("Failed to copy to {to:?}");
+            false
+        }
+    }
+}
+
+fn create_parent_dir(path: &Path) -> bool {
+    let parent_dir = match path.parent() {
+        Some(dir) => dir,
+        None => {
+            eprintln!("Failed to get parent dir of {path:?}");
+            return false;
+        }
+    };
+
+    match create_dir_all(parent_dir) {
+        Ok(_) => true,
+        Err(_) => {
+            eprintln!("Failed to create dir {parent_dir:?}");
+            false
+        }
+    }
+}
+
+/// Makes the characters of a path that patterns give a meaning to literal.
+fn escape_glob(path: &str) -> String {
+    let mut escaped = String::new();
+    for c in path.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
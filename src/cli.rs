@@ -0,0 +1,244 @@
+use std::{fmt, path::PathBuf};
+use util::log::Verbosity;
+
+pub const USAGE: &str = "\
+Usage: wikiup <command> [options]
+
+Commands:
+  build    Build the wiki
+  serve    Build the wiki and serve it
+  check    Report broken links and invalid files without writing anything
+
+Options:
+  --root <dir>      Directory with the markdown files [default: .]
+  --out <dir>       Directory to write the wiki to [default: <temp dir>/wikiup]
+  --addr <addr>     Address to serve on [default: 127.0.0.1]
+  --port <port>     Port to serve on [default: 8080]
+  --watch           Rebuild and reload pages when files change (serve)
+  --clean           Delete the output directory and build from scratch
+  --threads <n>     Number of threads [default: available parallelism]
+  -q, --quiet       Only print errors
+  -v, --verbose     Print every file written and request served
+  -h, --help        Print this help
+  -V, --version     Print the version
+
+Exits with 1 if any page failed or `check` found problems, and 2 for usage
+errors.
+
+Options other than --root can also be set in `wikiup.toml` in the root, and
+the ones given here take precedence.";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Build,
+    /// `None` takes the address and port from the config file, or the
+    /// defaults.
+    Serve {
+        addr: Option<String>,
+        port: Option<u16>,
+        watch: bool,
+    },
+    Check,
+    Help,
+    Version,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    pub root: PathBuf,
+    /// `None` takes the output directory from the config file, or the default.
+    pub out: Option<PathBuf>,
+    /// Delete the output directory before building.
+    pub clean: bool,
+    /// `None` uses the available parallelism.
+    pub threads: Option<usize>,
+    pub verbosity: Verbosity,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CliError {
+    pub message: String,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+fn error(message: impl Into<String>) -> CliError {
+    CliError {
+        message: message.into(),
+    }
+}
+
+/// Parses the arguments after the program name. Options can come before or
+/// after the command, and take their value as `--out dir` or `--out=dir`.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Cli, CliError> {
+    let mut command = None;
+    let mut root = None;
+    let mut out = None;
+    let mut addr = None;
+    let mut port = None;
+    let mut watch = false;
+    let mut clean = false;
+    let mut threads = None;
+    let mut quiet = false;
+    let mut verbose = false;
+    let mut help = false;
+    let mut version = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| match inline_value {
+            Some(value) => Ok(value.to_string()),
+            None => args
+                .next()
+                .ok_or_else(|| error(format!("`{name}` needs a value"))),
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => help = true,
+            "-V" | "--version" => version = true,
+            "--root" => root = Some(PathBuf::from(value("--root")?)),
+            "--out" => out = Some(PathBuf::from(value("--out")?)),
+            "--addr" => addr = Some(value("--addr")?),
+            "--port" => {
+                let text = value("--port")?;
+                port = Some(
+                    text.parse::<u16>()
+                        .map_err(|_| error(format!("invalid port `{text}`")))?,
+                );
+            }
+            "--threads" => {
+                let text = value("--threads")?;
+                threads = match text.parse::<usize>() {
+                    Ok(threads) if threads > 0 => Some(threads),
+                    _ => return Err(error(format!("invalid number of threads `{text}`"))),
+                };
+            }
+            "--watch" => watch = true,
+            "--clean" => clean = true,
+            "-q" | "--quiet" => quiet = true,
+            "-v" | "--verbose" => verbose = true,
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(error(format!("unknown option `{flag}`")))
+            }
+            name if command.is_none() => command = Some(name.to_string()),
+            name => return Err(error(format!("unexpected argument `{name}`"))),
+        }
+    }
+
+    let command = match command.as_deref() {
+        _ if help => Command::Help,
+        _ if version => Command::Version,
+        Some("serve") => Command::Serve { addr, port, watch },
+        Some(name @ ("build" | "check")) => {
+            for (flag, given) in [
+                ("--addr", addr.is_some()),
+                ("--port", port.is_some()),
+                ("--watch", watch),
+            ] {
+                if given {
+                    return Err(error(format!("`{flag}` only applies to `serve`")));
+                }
+            }
+            match name {
+                "build" => Command::Build,
+                _ => Command::Check,
+            }
+        }
+        Some(name) => return Err(error(format!("unknown command `{name}`"))),
+        None => return Err(error("missing command")),
+    };
+    if clean && command == Command::Check {
+        return Err(error("`--clean` only applies to `build` and `serve`"));
+    }
+    if quiet && verbose {
+        return Err(error("`--quiet` and `--verbose` can't be combined"));
+    }
+
+    Ok(Cli {
+        command,
+        root: root.unwrap_or_else(|| PathBuf::from(".")),
+        out,
+        clean,
+        threads,
+        verbosity: match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (_, true) => Verbosity::Verbose,
+            _ => Verbosity::Normal,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, CliError> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn commands() {
+        let cli = parse("build").unwrap();
+        assert_eq!(cli.command, Command::Build);
+        assert_eq!(cli.root, PathBuf::from("."));
+        assert_eq!(cli.out, None);
+        assert!(!cli.clean);
+        assert_eq!(cli.threads, None);
+        assert_eq!(cli.verbosity, Verbosity::Normal);
+
+        let cli = parse("--verbose serve --port=3000 --watch --root docs --threads 4").unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Serve {
+                addr: None,
+                port: Some(3000),
+                watch: true,
+            }
+        );
+        assert_eq!(cli.root, PathBuf::from("docs"));
+        assert_eq!(cli.threads, Some(4));
+        assert_eq!(cli.verbosity, Verbosity::Verbose);
+
+        let cli = parse("check -q --out site").unwrap();
+        assert_eq!(cli.command, Command::Check);
+        assert_eq!(cli.out, Some(PathBuf::from("site")));
+        assert_eq!(cli.verbosity, Verbosity::Quiet);
+
+        assert!(parse("build --clean").unwrap().clean);
+
+        assert_eq!(parse("serve --help").unwrap().command, Command::Help);
+        assert_eq!(parse("--help").unwrap().command, Command::Help);
+        assert_eq!(parse("-V").unwrap().command, Command::Version);
+    }
+
+    #[test]
+    fn errors() {
+        for (args, message) in [
+            ("", "missing command"),
+            ("publish", "unknown command `publish`"),
+            ("build extra", "unexpected argument `extra`"),
+            ("build --fast", "unknown option `--fast`"),
+            ("build --out", "`--out` needs a value"),
+            ("build --port 80", "`--port` only applies to `serve`"),
+            ("check --watch", "`--watch` only applies to `serve`"),
+            (
+                "check --clean",
+                "`--clean` only applies to `build` and `serve`",
+            ),
+            ("serve --port 99999", "invalid port `99999`"),
+            ("build --threads 0", "invalid number of threads `0`"),
+            ("build -q -v", "`--quiet` and `--verbose` can't be combined"),
+        ] {
+            assert_eq!(parse(args).err().unwrap().message, message, "{args}");
+        }
+    }
+}
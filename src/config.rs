@@ -0,0 +1,473 @@
+use markdown::Extensions;
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::read_to_string,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use util::io::SymlinkPolicy;
+
+/// The optional configuration file in the root.
+pub const CONFIG_FILE: &str = "wikiup.toml";
+
+/// Settings from `wikiup.toml`. Everything is optional, and command-line
+/// options take precedence over the ones here.
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    /// The name of the wiki, shown in the header and page titles.
+    pub title: Option<String>,
+    /// Where the wiki is published, for canonical links.
+    pub base_url: Option<String>,
+    /// The output directory, relative to the root.
+    pub out: Option<PathBuf>,
+    /// Patterns in gitignore syntax of files to leave out of the wiki, on
+    /// top of `.gitignore` and `.wikiupignore`.
+    pub ignore: Vec<String>,
+    /// Whether to follow symbolic links when finding files.
+    pub symlinks: SymlinkPolicy,
+    /// How many directories deep to find files below the root.
+    pub max_depth: Option<usize>,
+    /// A directory, relative to the root, with layouts and files that replace
+    /// the built-in theme's.
+    pub theme: Option<PathBuf>,
+    pub addr: Option<String>,
+    pub port: Option<u16>,
+    pub extensions: Extensions,
+    /// Pages and directories, relative to the root, that the navigation lists
+    /// first and in this order.
+    pub navigation: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    /// The line of the file the error is on, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn error(line: usize, message: impl Into<String>) -> ConfigError {
+    ConfigError {
+        line,
+        message: message.into(),
+    }
+}
+
+/// The tables keys can be in, besides the top level.
+const TABLES: &[&str] = &["server", "markdown"];
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+/// Reads `wikiup.toml` from the root, or the defaults if there is none.
+/// Errors have already been printed when this returns `None`.
+pub fn read_config(root: &Path) -> Option<Config> {
+    let path = root.join(CONFIG_FILE);
+    match read_to_string(&path) {
+        Ok(text) => match parse_config(&text) {
+            Ok(config) => Some(config),
+            Err(err) => {
+                eprintln!("Invalid {CONFIG_FILE}, {err}");
+                None
+            }
+        },
+        Err(err) if err.kind() == ErrorKind::NotFound => Some(Config::default()),
+        Err(err) => {
+            eprintln!("Failed to read {path:?}: {err}");
+            None
+        }
+    }
+}
+
+/// Parses `wikiup.toml`, which uses a subset of TOML: `key = value` lines
+/// with strings, integers, booleans and arrays, and `[server]` and
+/// `[markdown]` tables. Unknown keys and tables are errors, so typos don't go
+/// unnoticed.
+pub fn parse_config(text: &str) -> Result<Config, ConfigError> {
+    let mut config = Config::default();
+    let mut table: Option<&str> = None;
+    let mut seen_tables = HashSet::new();
+    let mut seen_keys = HashSet::new();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let number = i + 1;
+        let line = lines[i].trim();
+        i += 1;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let (name, rest) = header
+                .split_once(']')
+                .ok_or_else(|| error(number, "expected `]` after the table name"))?;
+            let name = name.trim();
+            let rest = rest.trim();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(error(number, format!("unexpected `{rest}` after table")));
+            }
+            let Some(name) = TABLES.iter().find(|table| **table == name) else {
+                return Err(error(number, format!("unknown table `[{name}]`")));
+            };
+            if !seen_tables.insert(*name) {
+                return Err(error(number, format!("table `[{name}]` is given twice")));
+            }
+            table = Some(name);
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(error(number, "expected `key = value`"));
+        };
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(error(number, format!("invalid key `{key}`")));
+        }
+        let name = match table {
+            Some(table) => format!("{table}.{key}"),
+            None => key.to_string(),
+        };
+        if !seen_keys.insert(name.clone()) {
+            return Err(error(number, format!("`{name}` is given twice")));
+        }
+
+        // Arrays can continue over several lines until they are closed.
+        let mut value = value.trim().to_string();
+        while !is_complete(&value) && i < lines.len() {
+            value.push('\n');
+            value.push_str(lines[i]);
+            i += 1;
+        }
+        let value = parse_value(&value).map_err(|message| error(number, message))?;
+        set(&mut config, &name, value).map_err(|message| error(number, message))?;
+    }
+    Ok(config)
+}
+
+/// Stores a value in the config, or explains why it doesn't fit.
+fn set(config: &mut Config, name: &str, value: Value) -> Result<(), String> {
+    let expected = |what: &str| format!("`{name}` should be {what}");
+    let string = |value: Value| match value {
+        Value::String(s) => Ok(s),
+        _ => Err(expected("a string")),
+    };
+    let strings = |value: Value| match value {
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s),
+                _ => Err(expected("an array of strings")),
+            })
+            .collect(),
+        _ => Err(expected("an array of strings")),
+    };
+
+    match name {
+        "title" => config.title = Some(string(value)?),
+        "base_url" => config.base_url = Some(string(value)?),
+        "out" => config.out = Some(PathBuf::from(string(value)?)),
+        "ignore" => config.ignore = strings(value)?,
+        "symlinks" => {
+            let name = string(value)?;
+            let Some(policy) = SymlinkPolicy::from_name(&name) else {
+                return Err(format!(
+                    "unknown symlink policy `{name}`, expected one of {}",
+                    SymlinkPolicy::NAMES.join(", ")
+                ));
+            };
+            config.symlinks = policy;
+        }
+        "max_depth" => {
+            config.max_depth = match value {
+                Value::Integer(depth) => usize::try_from(depth).ok(),
+                _ => None,
+            };
+            if config.max_depth.is_none() {
+                return Err(expected("a number of directories"));
+            }
+        }
+        "theme" => config.theme = Some(PathBuf::from(string(value)?)),
+        "navigation" => config.navigation = strings(value)?,
+        "server.addr" => config.addr = Some(string(value)?),
+        "server.port" => {
+            config.port = match value {
+                Value::Integer(port) => u16::try_from(port).ok().filter(|port| *port > 0),
+                _ => None,
+            };
+            if config.port.is_none() {
+                return Err(expected("a port number between 1 and 65535"));
+            }
+        }
+        "markdown.extensions" => {
+            let mut extensions = Extensions::none();
+            for extension in strings(value)? {
+                if !extensions.enable(&extension) {
+                    return Err(format!(
+                        "unknown extension `{extension}`, expected one of {}",
+                        Extensions::NAMES.join(", ")
+                    ));
+                }
+            }
+            config.extensions = extensions;
+        }
+        _ => {
+            return Err(match name.split_once('.') {
+                Some((table, key)) => format!("unknown key `{key}` in `[{table}]`"),
+                None => format!("unknown key `{name}`"),
+            })
+        }
+    }
+    Ok(())
+}
+
+/// Whether every array in a value is closed.
+fn is_complete(value: &str) -> bool {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, '#') => {
+                chars.find(|&c| c == '\n');
+            }
+            (None, _) => (),
+        }
+    }
+    depth <= 0
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let (value, rest) = parse_item(text)?;
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(value)
+    } else {
+        Err(format!("unexpected `{rest}` after value"))
+    }
+}
+
+/// Parses a value at the start of `text`, returning it and the text after it.
+fn parse_item(text: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = text.strip_prefix('[') {
+        return parse_array(rest);
+    }
+    if let Some(rest) = text.strip_prefix('"') {
+        return parse_basic_string(rest);
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find(['\'', '\n']).ok_or("unterminated string")?;
+        if !rest[end..].starts_with('\'') {
+            return Err("unterminated string".into());
+        }
+        return Ok((Value::String(rest[..end].to_string()), &rest[end + 1..]));
+    }
+
+    let end = text
+        .find(|c: char| c == ',' || c == ']' || c == '#' || c.is_whitespace())
+        .unwrap_or(text.len());
+    let value = match &text[..end] {
+        "" => return Err("expected a value".into()),
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        token => match token.replace('_', "").parse() {
+            Ok(n) => Value::Integer(n),
+            Err(_) => return Err(format!("invalid value `{token}`, strings need quotes")),
+        },
+    };
+    Ok((value, &text[end..]))
+}
+
+fn parse_array(mut rest: &str) -> Result<(Value, &str), String> {
+    let mut items = Vec::new();
+    loop {
+        rest = skip_whitespace(rest);
+        if let Some(after) = rest.strip_prefix(']') {
+            return Ok((Value::Array(items), after));
+        }
+        if rest.is_empty() {
+            return Err("unterminated array".into());
+        }
+
+        let (item, after) = parse_item(rest)?;
+        items.push(item);
+        rest = skip_whitespace(after);
+        match rest.strip_prefix(',') {
+            Some(after) => rest = after,
+            None if rest.starts_with(']') => (),
+            None if rest.is_empty() => return Err("unterminated array".into()),
+            None => return Err("expected `,` or `]` in array".into()),
+        }
+    }
+}
+
+/// Skips whitespace, line breaks and comments between array items.
+fn skip_whitespace(mut text: &str) -> &str {
+    loop {
+        text = text.trim_start();
+        match text.strip_prefix('#') {
+            Some(comment) => text = comment.split_once('\n').map_or("", |(_, rest)| rest),
+            None => return text,
+        }
+    }
+}
+
+fn parse_basic_string(text: &str) -> Result<(Value, &str), String> {
+    let mut output = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((Value::String(output), &text[i + 1..])),
+            '\n' => break,
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('n') => output.push('\n'),
+                Some('t') => output.push('\t'),
+                Some('r') => output.push('\r'),
+                Some(c @ ('\\' | '"')) => output.push(c),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("invalid escape `\\u{hex}`"))?;
+                    output.push(c);
+                }
+                Some(c) => return Err(format!("invalid escape `\\{c}`")),
+                None => break,
+            },
+            c => output.push(c),
+        }
+    }
+    Err("unterminated string".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config() {
+        let config = parse_config(
+            r#"
+# Our wiki
+title = "Team \"Ops\" wiki"
+base_url = 'https://wiki.example.com/'
+out = "_site"
+ignore = [
+    "drafts/**", # not ready
+    "*.tmp",
+]
+symlinks = "skip"
+max_depth = 8
+theme = "theme"
+navigation = ["index.md", "guides", "reference"]
+
+[server]
+addr = "0.0.0.0"
+port = 3_000
+
+[markdown]
+extensions = ["tables", "wiki_links"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                title: Some("Team \"Ops\" wiki".into()),
+                base_url: Some("https://wiki.example.com/".into()),
+                out: Some("_site".into()),
+                ignore: vec!["drafts/**".into(), "*.tmp".into()],
+                symlinks: SymlinkPolicy::Skip,
+                max_depth: Some(8),
+                theme: Some("theme".into()),
+                addr: Some("0.0.0.0".into()),
+                port: Some(3000),
+                extensions: Extensions {
+                    tables: true,
+                    wiki_links: true,
+                    ..Extensions::none()
+                },
+                navigation: vec!["index.md".into(), "guides".into(), "reference".into()],
+            }
+        );
+
+        assert_eq!(parse_config("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn errors() {
+        for (text, line, message) in [
+            ("titel = \"x\"", 1, "unknown key `titel`"),
+            (
+                "\n[server]\nhost = \"x\"",
+                3,
+                "unknown key `host` in `[server]`",
+            ),
+            ("[site]", 1, "unknown table `[site]`"),
+            ("[server]\n[server]", 2, "table `[server]` is given twice"),
+            ("title = \"a\"\ntitle = \"b\"", 2, "`title` is given twice"),
+            (
+                "title = Wiki",
+                1,
+                "invalid value `Wiki`, strings need quotes",
+            ),
+            ("title = 1", 1, "`title` should be a string"),
+            (
+                "[server]\nport = 70000",
+                2,
+                "`server.port` should be a port number between 1 and 65535",
+            ),
+            (
+                "ignore = [\"a\", 1]",
+                1,
+                "`ignore` should be an array of strings",
+            ),
+            ("ignore = [\n\"a\"\n", 1, "unterminated array"),
+            (
+                "symlinks = \"always\"",
+                1,
+                "unknown symlink policy `always`, expected one of follow, skip, within-root",
+            ),
+            (
+                "max_depth = -1",
+                1,
+                "`max_depth` should be a number of directories",
+            ),
+            ("title = \"a", 1, "unterminated string"),
+            ("title = \"a\" b", 1, "unexpected `b` after value"),
+            ("just text", 1, "expected `key = value`"),
+            (
+                "[markdown]\nextensions = [\"emoji\"]",
+                2,
+                "unknown extension `emoji`, expected one of tables, admonitions, wiki_links, toc",
+            ),
+        ] {
+            assert_eq!(parse_config(text), Err(error(line, message)), "{text}");
+        }
+    }
+}
@@ -0,0 +1,806 @@
+use markdown::{escape_attribute, escape_html, render_toc, Metadata, Rendered, TocEntry};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{read_dir, read_to_string, DirEntry},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use util::hash::{Fnv1a, StableHash};
+
+use crate::wiki::relative_url;
+
+/// The directory under the root user layouts are read from. `page.html` in it
+/// replaces the built-in layout, and pages can pick any other one with
+/// `layout: name` in their front matter.
+pub const LAYOUTS_DIR: &str = "_layouts";
+
+/// Where the built-in stylesheet is written to in the output.
+pub const STYLESHEET_PATH: &str = "_wikiup/style.css";
+
+/// Where the search index is written to in the output, which the search UI
+/// of the built-in layout loads.
+pub const SEARCH_INDEX_PATH: &str = "_wikiup/search-index.json";
+
+/// The files of the built-in theme, and where they go in the output.
+pub const THEME_FILES: &[(&str, &str)] = &[
+    (STYLESHEET_PATH, include_str!("theme/style.css")),
+    ("_wikiup/search.js", include_str!("theme/search.js")),
+];
+
+const DEFAULT_LAYOUT: &str = include_str!("theme/page.html");
+
+/// The variables layouts can use. `content`, `toc`, `breadcrumbs` and
+/// `navigation` are HTML and inserted as is, everything else is escaped.
+const VARIABLES: &[&str] = &[
+    "title",
+    "content",
+    "toc",
+    "breadcrumbs",
+    "navigation",
+    "site_title",
+    "canonical_url",
+    "description",
+    "tags",
+    "authors",
+    "last_modified",
+    "root",
+    "stylesheet",
+];
+
+/// Variables holding lists, which `{{#each}}` goes through.
+const LIST_VARIABLES: &[&str] = &["tags", "authors"];
+
+pub enum Value {
+    Text(String),
+    Html(String),
+    List(Vec<String>),
+}
+
+impl Value {
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::Text(s) | Value::Html(s) => s.is_empty(),
+            Value::List(items) => items.is_empty(),
+        }
+    }
+}
+
+pub type Context = HashMap<&'static str, Value>;
+
+#[derive(Debug, PartialEq)]
+pub struct TemplateError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Variable(String),
+    /// The current item of an `{{#each}}`.
+    This,
+    If {
+        name: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        name: String,
+        body: Vec<Node>,
+    },
+}
+
+/// A layout with `{{variable}}`s, `{{#if variable}}...{{else}}...{{/if}}`
+/// blocks, which check the variable isn't empty, and
+/// `{{#each list}}...{{this}}...{{/each}}` loops.
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+    /// What it was parsed from, which is what tells layouts apart in the
+    /// build manifest.
+    source: String,
+}
+
+/// An `{{#if}}` or `{{#each}}` whose closing tag hasn't been reached yet.
+struct OpenBlock<'a> {
+    keyword: &'a str,
+    name: &'a str,
+    line: usize,
+    nodes: Vec<Node>,
+    /// The nodes before the `{{else}}` of an `{{#if}}` that has one.
+    then: Option<Vec<Node>>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut nodes = Vec::new();
+        let mut open: Vec<OpenBlock> = Vec::new();
+        let mut line = 1;
+        let mut rest = source;
+
+        loop {
+            let Some(start) = rest.find("{{") else {
+                if !rest.is_empty() {
+                    current_nodes(&mut open, &mut nodes).push(Node::Text(rest.to_string()));
+                }
+                break;
+            };
+
+            let (text, after) = rest.split_at(start);
+            if !text.is_empty() {
+                current_nodes(&mut open, &mut nodes).push(Node::Text(text.to_string()));
+            }
+            line += text.matches('\n').count();
+            let error = move |message: String| TemplateError { line, message };
+            let end = after
+                .find("}}")
+                .ok_or_else(|| error("`{{` is never closed".into()))?;
+            let tag = after[2..end].trim();
+            line += tag.matches('\n').count();
+            rest = &after[end + 2..];
+
+            match tag.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [keyword @ ("#if" | "#each"), name] => {
+                    if !VARIABLES.contains(name) {
+                        return Err(error(format!("unknown variable `{name}`")));
+                    }
+                    if *keyword == "#each" && !LIST_VARIABLES.contains(name) {
+                        return Err(error(format!("`{name}` isn't a list")));
+                    }
+                    open.push(OpenBlock {
+                        keyword: &keyword[1..],
+                        name,
+                        line,
+                        nodes: Vec::new(),
+                        then: None,
+                    });
+                }
+                ["else"] => match open.last_mut() {
+                    Some(block) if block.keyword == "if" && block.then.is_none() => {
+                        block.then = Some(std::mem::take(&mut block.nodes));
+                    }
+                    _ => return Err(error("`{{else}}` outside of `{{#if}}`".into())),
+                },
+                [closing @ ("/if" | "/each")] => {
+                    let block = match open.pop() {
+                        Some(block) if block.keyword == &closing[1..] => block,
+                        _ => {
+                            return Err(error(format!(
+                                "`{{{{{closing}}}}}` without a block to close"
+                            )))
+                        }
+                    };
+                    let name = block.name.to_string();
+                    let node = match (block.keyword, block.then) {
+                        ("if", Some(then)) => Node::If {
+                            name,
+                            then,
+                            otherwise: block.nodes,
+                        },
+                        ("if", None) => Node::If {
+                            name,
+                            then: block.nodes,
+                            otherwise: Vec::new(),
+                        },
+                        _ => Node::Each {
+                            name,
+                            body: block.nodes,
+                        },
+                    };
+                    current_nodes(&mut open, &mut nodes).push(node);
+                }
+                ["this"] => {
+                    if !open.iter().any(|block| block.keyword == "each") {
+                        return Err(error("`{{this}}` outside of `{{#each}}`".into()));
+                    }
+                    current_nodes(&mut open, &mut nodes).push(Node::This);
+                }
+                [name] if VARIABLES.contains(name) => {
+                    current_nodes(&mut open, &mut nodes).push(Node::Variable(name.to_string()));
+                }
+                _ => return Err(error(format!("unknown tag `{{{{{tag}}}}}`"))),
+            }
+        }
+
+        if let Some(block) = open.pop() {
+            return Err(TemplateError {
+                line: block.line,
+                message: format!(
+                    "`{{{{#{} {}}}}}` is never closed",
+                    block.keyword, block.name
+                ),
+            });
+        }
+        Ok(Self {
+            nodes,
+            source: source.to_string(),
+        })
+    }
+
+    pub fn render(&self, context: &Context) -> String {
+        let mut output = String::new();
+        render_nodes(&mut output, &self.nodes, context, None);
+        output
+    }
+}
+
+impl StableHash for Template {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.source.stable_hash(hasher);
+    }
+}
+
+/// The nodes of the innermost open block, or the top-level ones.
+fn current_nodes<'a>(open: &'a mut [OpenBlock], nodes: &'a mut Vec<Node>) -> &'a mut Vec<Node> {
+    match open.last_mut() {
+        Some(block) => &mut block.nodes,
+        None => nodes,
+    }
+}
+
+fn render_nodes(output: &mut String, nodes: &[Node], context: &Context, item: Option<&str>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable(name) => match context.get(name.as_str()) {
+                Some(Value::Text(text)) => output.push_str(&escape_attribute(text)),
+                Some(Value::Html(html)) => output.push_str(html),
+                Some(Value::List(items)) => output.push_str(&escape_attribute(&items.join(", "))),
+                None => (),
+            },
+            Node::This => output.push_str(&escape_attribute(item.unwrap_or_default())),
+            Node::If {
+                name,
+                then,
+                otherwise,
+            } => {
+                let set = context
+                    .get(name.as_str())
+                    .is_some_and(|value| !value.is_empty());
+                let nodes = if set { then } else { otherwise };
+                render_nodes(output, nodes, context, item);
+            }
+            Node::Each { name, body } => {
+                if let Some(Value::List(items)) = context.get(name.as_str()) {
+                    for item in items {
+                        render_nodes(output, body, context, Some(item));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The built-in layout and the ones in the root's `_layouts` directory.
+pub struct Layouts {
+    default: Template,
+    custom: HashMap<String, Template>,
+    /// Layouts that couldn't be read or parsed, relative to the root.
+    invalid: Vec<PathBuf>,
+}
+
+impl Layouts {
+    /// Loads the layouts in the theme directory, if there is one, and then
+    /// the ones in `_layouts`, which win over the theme's. Layouts that fail
+    /// to parse are skipped.
+    pub fn load(root: &Path, theme: Option<&Path>) -> Self {
+        let mut layouts = Self {
+            default: Template::parse(DEFAULT_LAYOUT).expect("built-in layout is valid"),
+            custom: HashMap::new(),
+            invalid: Vec::new(),
+        };
+        if let Some(theme) = theme {
+            layouts.load_dir(root, theme);
+        }
+        layouts.load_dir(root, Path::new(LAYOUTS_DIR));
+        layouts
+    }
+
+    /// Loads the `.html` files in a directory relative to the root.
+    fn load_dir(&mut self, root: &Path, dir: &Path) {
+        let mut entries: Vec<DirEntry> = match read_dir(root.join(dir)) {
+            Ok(entries) => entries.flatten().collect(),
+            Err(_) => return,
+        };
+        // Sorted, so errors come in the same order every build.
+        entries.sort_by_key(DirEntry::file_name);
+        for entry in entries {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "html") {
+                continue;
+            }
+            let Some(name) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+
+            let source = match read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => {
+                    eprintln!("Failed to read layout {path:?}: {err}");
+                    self.invalid.push(dir.join(entry.file_name()));
+                    continue;
+                }
+            };
+            match Template::parse(&source) {
+                Ok(template) => {
+                    self.custom.insert(name, template);
+                }
+                Err(err) => {
+                    eprintln!("Invalid layout {path:?}, {err}");
+                    self.invalid.push(dir.join(entry.file_name()));
+                }
+            }
+        }
+    }
+
+    /// The layout pages use unless they pick another one.
+    pub fn page(&self) -> &Template {
+        self.custom.get("page").unwrap_or(&self.default)
+    }
+
+    pub fn named(&self, name: &str) -> Option<&Template> {
+        self.custom.get(name)
+    }
+
+    pub fn invalid(&self) -> &[PathBuf] {
+        &self.invalid
+    }
+}
+
+/// The title of a page: the one from its front matter, or else its first
+/// top-level heading, or else its file name.
+pub fn page_title(path: &Path, metadata: &Metadata, toc: &[TocEntry]) -> String {
+    if let Some(title) = &metadata.title {
+        return title.clone();
+    }
+    match toc.iter().find(|entry| entry.level == 1) {
+        Some(entry) => entry.text.clone(),
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// What every page shows about the wiki as a whole.
+#[derive(Default)]
+pub struct SiteInfo {
+    pub title: Option<String>,
+    /// Where the wiki is published, for canonical links.
+    pub base_url: Option<String>,
+}
+
+/// The variables for the page generated from `path`.
+pub fn page_context(
+    path: &Path,
+    rendered: &Rendered,
+    site: &SiteInfo,
+    breadcrumbs: String,
+    navigation: String,
+    last_modified: Option<SystemTime>,
+) -> Context {
+    let metadata = &rendered.metadata;
+    let root = root_url(path);
+    let canonical_url = site.base_url.as_ref().map(|base_url| {
+        format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            relative_url(Path::new(""), path)
+        )
+    });
+    Context::from([
+        (
+            "title",
+            Value::Text(page_title(path, metadata, &rendered.toc)),
+        ),
+        ("content", Value::Html(rendered.html.clone())),
+        ("toc", Value::Html(render_toc(&rendered.toc))),
+        ("breadcrumbs", Value::Html(breadcrumbs)),
+        ("navigation", Value::Html(navigation)),
+        (
+            "site_title",
+            Value::Text(site.title.clone().unwrap_or_default()),
+        ),
+        (
+            "canonical_url",
+            Value::Text(canonical_url.unwrap_or_default()),
+        ),
+        (
+            "description",
+            Value::Text(metadata.description.clone().unwrap_or_default()),
+        ),
+        ("tags", Value::List(metadata.tags.clone())),
+        ("authors", Value::List(metadata.authors.clone())),
+        (
+            "last_modified",
+            Value::Text(last_modified.map(format_date).unwrap_or_default()),
+        ),
+        (
+            "stylesheet",
+            Value::Text(format!("{root}{STYLESHEET_PATH}")),
+        ),
+        ("root", Value::Text(root)),
+    ])
+}
+
+/// The relative URL of the root from the page generated for `path`, such as
+/// `../` for `guides/setup.md`. Empty for pages in the root.
+pub fn root_url(path: &Path) -> String {
+    let depth = path
+        .parent()
+        .map_or(0, |parent| parent.components().count());
+    "../".repeat(depth)
+}
+
+/// Renders the trail of links from the root to the page: the root's
+/// `index.md` first, then every directory the page is in, linked when it has
+/// an `index.md`. Empty for the root's own `index.md`.
+pub fn breadcrumbs(path: &Path, title: &str, has_page: impl Fn(&Path) -> bool) -> String {
+    let mut dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+    dirs.pop(); // The root itself.
+    dirs.reverse();
+    // An index page stands for its directory.
+    let is_index = path.file_stem().is_some_and(|stem| stem == "index");
+    if is_index && dirs.pop().is_none() {
+        return String::new();
+    }
+
+    let crumb = |label: &str, index: &Path| {
+        let label = escape_html(label);
+        if has_page(index) && index != path {
+            let url = escape_attribute(&relative_url(path, index));
+            format!("<li><a href=\"{url}\">{label}</a></li>")
+        } else {
+            format!("<li>{label}</li>")
+        }
+    };
+
+    let mut html = String::from("<nav class=\"breadcrumbs\" aria-label=\"Breadcrumbs\"><ol>");
+    html.push_str(&crumb("Home", Path::new("index.md")));
+    for dir in dirs {
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        html.push_str(&crumb(&name, &dir.join("index.md")));
+    }
+    html.push_str("<li aria-current=\"page\">");
+    html.push_str(&escape_html(title));
+    html.push_str("</li></ol></nav>");
+    html
+}
+
+/// A page the navigation lists.
+pub struct NavPage<'a> {
+    pub path: &'a Path,
+    pub title: &'a str,
+    /// The `order` from its front matter.
+    pub order: Option<i64>,
+}
+
+/// A page or directory in the navigation. Directories link to their
+/// `index.md` when they have one and take its title and order.
+#[derive(Debug, PartialEq)]
+pub struct NavItem {
+    pub title: String,
+    /// The page it links to.
+    pub page: Option<PathBuf>,
+    pub children: Vec<NavItem>,
+    /// The page or directory path.
+    key: PathBuf,
+    is_dir: bool,
+    order: Option<i64>,
+}
+
+impl StableHash for NavItem {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        (&self.title, &self.page, &self.children).stable_hash(hasher);
+        (&self.key, self.is_dir, self.order).stable_hash(hasher);
+    }
+}
+
+/// Arranges pages into a tree by directory. Each level lists the paths in
+/// `first` in that order, then the rest by front matter `order` and title.
+/// `first` can name pages with or without `.md`, and directories.
+pub fn navigation_tree(pages: &[NavPage], first: &[String]) -> Vec<NavItem> {
+    let mut items = Vec::new();
+    for page in pages {
+        let dir = page.path.parent().unwrap_or(Path::new(""));
+        let is_index = page.path.file_stem().is_some_and(|stem| stem == "index");
+
+        let mut level = &mut items;
+        let mut dirs: Vec<&Path> = dir.ancestors().collect();
+        dirs.pop(); // The root itself.
+        dirs.reverse();
+        // A directory's index page fills in the directory's item.
+        let own_dir = if is_index { dirs.pop() } else { None };
+        for dir in dirs {
+            level = &mut directory_item(level, dir).children;
+        }
+        match own_dir {
+            Some(dir) => {
+                let item = directory_item(level, dir);
+                item.title = page.title.to_string();
+                item.page = Some(page.path.to_path_buf());
+                item.order = page.order;
+            }
+            None => level.push(NavItem {
+                title: page.title.to_string(),
+                page: Some(page.path.to_path_buf()),
+                children: Vec::new(),
+                key: page.path.to_path_buf(),
+                is_dir: false,
+                order: page.order,
+            }),
+        }
+    }
+    sort_navigation(&mut items, first);
+    items
+}
+
+fn directory_item<'a>(items: &'a mut Vec<NavItem>, dir: &Path) -> &'a mut NavItem {
+    let position = items.iter().position(|item| item.is_dir && item.key == dir);
+    let position = position.unwrap_or_else(|| {
+        items.push(NavItem {
+            title: dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            page: None,
+            children: Vec::new(),
+            key: dir.to_path_buf(),
+            is_dir: true,
+            order: None,
+        });
+        items.len() - 1
+    });
+    &mut items[position]
+}
+
+fn sort_navigation(items: &mut [NavItem], first: &[String]) {
+    let rank = |item: &NavItem| {
+        first
+            .iter()
+            .position(|path| {
+                let path = Path::new(path.trim_matches('/'));
+                path == item.key || (!item.is_dir && path == item.key.with_extension(""))
+            })
+            .unwrap_or(usize::MAX)
+    };
+    items.sort_by_cached_key(|item| {
+        (
+            rank(item),
+            item.order.is_none(),
+            item.order,
+            item.title.to_lowercase(),
+        )
+    });
+    for item in items {
+        sort_navigation(&mut item.children, first);
+    }
+}
+
+/// Renders the navigation for the page generated from `path`, marking the
+/// page itself with `aria-current`.
+pub fn render_navigation(items: &[NavItem], path: &Path) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mut html = String::from("<nav class=\"site-nav\" aria-label=\"Pages\">");
+    push_navigation(&mut html, items, path);
+    html.push_str("</nav>");
+    html
+}
+
+fn push_navigation(html: &mut String, items: &[NavItem], path: &Path) {
+    html.push_str("<ul>");
+    for item in items {
+        let title = escape_html(&item.title);
+        html.push_str("<li>");
+        match &item.page {
+            Some(page) => {
+                let url = escape_attribute(&relative_url(path, page));
+                let current = if page == path {
+                    " aria-current=\"page\""
+                } else {
+                    ""
+                };
+                html.push_str(&format!("<a href=\"{url}\"{current}>{title}</a>"));
+            }
+            None => html.push_str(&format!("<span>{title}</span>")),
+        }
+        if !item.children.is_empty() {
+            push_navigation(html, &item.children, path);
+        }
+        html.push_str("</li>");
+    }
+    html.push_str("</ul>");
+}
+
+/// Formats a time as a `YYYY-MM-DD` date in UTC.
+pub fn format_date(time: SystemTime) -> String {
+    let days = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => (duration.as_secs() / 86400) as i64,
+        Err(err) => -(err.duration().as_secs().div_ceil(86400) as i64),
+    };
+
+    // Howard Hinnant's days_from_civil, inverted.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn context() -> Context {
+        Context::from([
+            ("title", Value::Text("Tom & \"Jerry\"".into())),
+            ("content", Value::Html("<p>Hi</p>".into())),
+            ("tags", Value::List(vec!["a".into(), "<b>".into()])),
+            ("authors", Value::List(vec![])),
+        ])
+    }
+
+    #[test]
+    fn variables() {
+        let template = Template::parse("<title>{{ title }}</title>{{content}}").unwrap();
+        assert_eq!(
+            template.render(&context()),
+            "<title>Tom &amp; &quot;Jerry&quot;</title><p>Hi</p>"
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        let template = Template::parse(concat!(
+            "{{#if tags}}<ul>{{#each tags}}<li>{{this}}</li>{{/each}}</ul>{{/if}}",
+            "{{#if authors}}by {{authors}}{{else}}anonymous{{/if}}",
+            "{{#if toc}}{{toc}}{{/if}}",
+        ))
+        .unwrap();
+        assert_eq!(
+            template.render(&context()),
+            "<ul><li>a</li><li>&lt;b&gt;</li></ul>anonymous"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source| Template::parse(source).unwrap_err();
+        assert_eq!(
+            error("a\n{{ titel }}"),
+            TemplateError {
+                line: 2,
+                message: "unknown tag `{{titel}}`".into()
+            }
+        );
+        assert_eq!(error("\n\n{{#if title}}\n").line, 3);
+        assert_eq!(
+            error("{{#each title}}{{/each}}").message,
+            "`title` isn't a list"
+        );
+        assert_eq!(
+            error("{{this}}").message,
+            "`{{this}}` outside of `{{#each}}`"
+        );
+        assert_eq!(
+            error("{{/if}}").message,
+            "`{{/if}}` without a block to close"
+        );
+        assert_eq!(error("{{#if title}}{{/each}}").line, 1);
+        assert_eq!(error("{{else}}").line, 1);
+        assert_eq!(error("{{#if title}}{{else}}{{else}}{{/if}}").line, 1);
+        assert_eq!(error("{{ title").message, "`{{` is never closed");
+    }
+
+    #[test]
+    fn default_layout() {
+        let layouts = Layouts::load(Path::new("/nonexistent"), None);
+        let html = layouts.page().render(&context());
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Tom &amp; &quot;Jerry&quot;</title>"));
+        assert!(html.contains("<li>&lt;b&gt;</li>"));
+        assert!(layouts.named("missing").is_none());
+    }
+
+    #[test]
+    fn breadcrumb_trails() {
+        let pages = ["index.md", "guides/index.md", "guides/ops/deploy.md"];
+        let has_page = |path: &Path| pages.iter().any(|page| Path::new(page) == path);
+
+        assert_eq!(breadcrumbs(Path::new("index.md"), "Home", has_page), "");
+        assert_eq!(
+            breadcrumbs(Path::new("guides/ops/deploy.md"), "Deploy", has_page),
+            concat!(
+                "<nav class=\"breadcrumbs\" aria-label=\"Breadcrumbs\"><ol>",
+                "<li><a href=\"../../index.html\">Home</a></li>",
+                "<li><a href=\"../index.html\">guides</a></li>",
+                "<li>ops</li>",
+                "<li aria-current=\"page\">Deploy</li></ol></nav>",
+            )
+        );
+        assert_eq!(
+            breadcrumbs(Path::new("guides/index.md"), "Guides", has_page),
+            concat!(
+                "<nav class=\"breadcrumbs\" aria-label=\"Breadcrumbs\"><ol>",
+                "<li><a href=\"../index.html\">Home</a></li>",
+                "<li aria-current=\"page\">Guides</li></ol></nav>",
+            )
+        );
+    }
+
+    #[test]
+    fn navigation() {
+        let page = |path, title, order| NavPage {
+            path: Path::new(path),
+            title,
+            order,
+        };
+        let pages = [
+            page("about.md", "About", None),
+            page("index.md", "Home", None),
+            page("guides/setup.md", "Setup", Some(2)),
+            page("guides/index.md", "Guides", None),
+            page("guides/deploy.md", "Deploy", Some(1)),
+            page("notes/todo.md", "Todo", None),
+        ];
+        let items = navigation_tree(&pages, &["index".into(), "guides/".into()]);
+        let titles: Vec<&str> = items.iter().map(|item| item.title.as_str()).collect();
+        assert_eq!(titles, ["Home", "Guides", "About", "notes"]);
+        assert_eq!(items[1].page, Some(PathBuf::from("guides/index.md")));
+        assert_eq!(items[1].children[0].title, "Deploy");
+        assert_eq!(items[3].page, None);
+
+        assert_eq!(
+            render_navigation(&items[3..], Path::new("notes/todo.md")),
+            concat!(
+                "<nav class=\"site-nav\" aria-label=\"Pages\"><ul><li><span>notes</span>",
+                "<ul><li><a href=\"todo.html\" aria-current=\"page\">Todo</a></li></ul>",
+                "</li></ul></nav>",
+            )
+        );
+        assert_eq!(render_navigation(&[], Path::new("index.md")), "");
+    }
+
+    #[test]
+    fn root_urls() {
+        assert_eq!(root_url(Path::new("index.md")), "");
+        assert_eq!(root_url(Path::new("a/b/c.md")), "../../");
+    }
+
+    #[test]
+    fn dates() {
+        let date = |secs| format_date(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_709_251_199), "2024-02-29");
+        assert_eq!(date(1_735_689_600), "2025-01-01");
+        assert_eq!(
+            format_date(UNIX_EPOCH - Duration::from_secs(1)),
+            "1969-12-31"
+        );
+    }
+}
@@ -1,68 +1,220 @@
-use markdown::to_html;
-use server::ServerConfig;
+use build::Site;
+use cli::{parse_args, Cli, Command, USAGE};
+use config::{read_config, Config};
+use manifest::MANIFEST_PATH;
+use server::{LiveReload, ServerConfig};
 use std::{
-    env::temp_dir,
-    fs::{create_dir_all, read_to_string, write},
-    path::PathBuf,
+    env::{args, temp_dir},
+    fs::remove_dir_all,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, RwLock},
+    thread::spawn,
+    time::Instant,
+};
+use util::{
+    log::{is_quiet, is_verbose, set_verbosity},
+    threads::{get_avaliable_parallelism, set_max_threads},
 };
-use util::{io::get_all_markdown_files, threads::set_max_threads};
 
-fn main() {
-    set_max_threads(20);
+mod build;
+mod cli;
+mod config;
+mod layout;
+mod manifest;
+mod watch;
+mod wiki;
 
-    let start = std::time::Instant::now();
-    let root = PathBuf::from(".");
-    let out = temp_dir().join("wikiup");
-    let file_rx = get_all_markdown_files(root.clone());
+fn main() -> ExitCode {
+    let cli = match parse_args(args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{err}\nRun `wikiup --help` for usage");
+            return ExitCode::from(2);
+        }
+    };
 
-    while let Ok(path) = file_rx.recv() {
-        if let Ok(markdown) = read_to_string(&path) {
-            let html = to_html(&markdown);
+    set_verbosity(cli.verbosity);
+    set_max_threads(
+        cli.threads
+            .unwrap_or_else(|| get_avaliable_parallelism().get()),
+    );
 
-            let path = match path.strip_prefix(&root) {
-                Ok(path) => path,
-                Err(_) => {
-                    eprintln!("Failed to strip prefix from {path:?}");
-                    continue;
-                }
+    match cli.command {
+        Command::Help => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Command::Version => {
+            println!("wikiup {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Command::Build => {
+            let Some((config, out)) = settings(&cli) else {
+                return ExitCode::from(2);
             };
+            if cli.clean && !clean(&cli.root, &out) {
+                return ExitCode::FAILURE;
+            }
+            let (site, _) = build(&cli.root, &out, config);
+            exit_code(site.failures())
+        }
+        Command::Check => {
+            let Some((config, out)) = settings(&cli) else {
+                return ExitCode::from(2);
+            };
+            check(&cli.root, &out, config)
+        }
+        Command::Serve {
+            ref addr,
+            port,
+            watch,
+        } => {
+            let Some((mut config, out)) = settings(&cli) else {
+                return ExitCode::from(2);
+            };
+            let addr = addr
+                .clone()
+                .or(config.addr.take())
+                .unwrap_or_else(|| "127.0.0.1".to_string());
+            let port = port.or(config.port).unwrap_or(8080);
+            if cli.clean && !clean(&cli.root, &out) {
+                return ExitCode::FAILURE;
+            }
+            let (site, search_index) = build(&cli.root, &out, config);
+            let failures = site.failures();
+            let search_index = Arc::new(RwLock::new(search_index));
 
-            let out_path = out.join(path).with_extension("html");
-
-            let out_parent_dir = match out_path.parent() {
-                Some(dir) => dir,
-                None => {
-                    eprintln!("Failed to get parent dir of {out_path:?}");
-                    continue;
+            let live_reload = match watch {
+                true => {
+                    let live_reload = Arc::new(LiveReload::default());
+                    let (watched_index, watched_reload) =
+                        (search_index.clone(), live_reload.clone());
+                    let root = cli.root.clone();
+                    spawn(move || watch::watch(&root, site, watched_index, watched_reload));
+                    Some(live_reload)
                 }
+                false => None,
             };
 
-            match create_dir_all(out_parent_dir) {
-                Ok(_) => (),
-                Err(_) => {
-                    eprintln!("Failed to create dir {out_parent_dir:?}");
-                    continue;
+            let server_config = ServerConfig {
+                addr,
+                port,
+                root: out,
+                search_index,
+                live_reload,
+                hidden: vec![PathBuf::from(MANIFEST_PATH)],
+            };
+            match server::serve(server_config) {
+                Ok(_) => exit_code(failures),
+                Err(e) => {
+                    eprintln!("Server stopped with error: {e}");
+                    ExitCode::FAILURE
                 }
             }
+        }
+    }
+}
 
-            match write(&out_path, html) {
-                Ok(_) => println!("Wrote {out_path:?}"),
-                Err(_) => {
-                    eprintln!("Failed to write to {out_path:?}");
-                    continue;
-                }
+/// Reads `wikiup.toml` from the root, if there is one, and works out the
+/// output directory, with command-line options taking precedence. Errors
+/// have already been printed when this returns `None`.
+fn settings(cli: &Cli) -> Option<(Config, PathBuf)> {
+    let config = read_config(&cli.root)?;
+
+    let out = match (&cli.out, &config.out) {
+        (Some(out), _) => out.clone(),
+        (None, Some(out)) => cli.root.join(out),
+        (None, None) => temp_dir().join("wikiup"),
+    };
+    Some((config, out))
+}
+
+fn build(root: &Path, out: &Path, config: Config) -> (Site, search::SearchIndex) {
+    let start = Instant::now();
+    let mut site = Site::load(root, out, config);
+    let search_index = site.build();
+
+    if !is_quiet() {
+        let unchanged = match site.unchanged_count() {
+            0 => String::new(),
+            n => format!(" ({n} unchanged)"),
+        };
+        println!(
+            "Built {} pages{unchanged} and copied {} assets to {:?} in {:?}",
+            site.page_count(),
+            site.asset_count(),
+            out,
+            start.elapsed()
+        );
+    }
+    if site.removed_count() > 0 && !is_quiet() {
+        println!("Removed {} stale files", site.removed_count());
+    }
+    print_skipped(&site);
+    if site.failures() > 0 {
+        eprintln!("{} files failed", site.failures());
+    }
+    (site, search_index)
+}
+
+/// Deletes the output directory, unless the root is in it. Errors have
+/// already been printed when this returns false.
+fn clean(root: &Path, out: &Path) -> bool {
+    if !out.exists() {
+        return true;
+    }
+    match (root.canonicalize(), out.canonicalize()) {
+        (Ok(root), Ok(out)) if root.starts_with(&out) => {
+            eprintln!("Refusing to clean {out:?}, it contains the root {root:?}");
+            return false;
+        }
+        _ => (),
+    }
+    match remove_dir_all(out) {
+        Ok(_) => {
+            if is_verbose() {
+                println!("Removed {out:?}");
             }
+            true
+        }
+        Err(err) => {
+            eprintln!("Failed to remove {out:?}: {err}");
+            false
         }
     }
+}
 
-    println!("Time taken: {:?}", start.elapsed());
+fn check(root: &Path, out: &Path, config: Config) -> ExitCode {
+    let site = Site::load(root, out, config);
+    let problems = site.check();
+    print_skipped(&site);
 
-    let server_config = ServerConfig {
-        addr: "127.0.0.1".to_string(),
-        port: 8080,
-    };
-    match server::dummy_server(server_config) {
-        Ok(_) => println!("Server stopped"),
-        Err(e) => eprintln!("Server stopped with error: {e}"),
+    if problems > 0 {
+        eprintln!(
+            "Checked {} pages, found {problems} problems",
+            site.page_count()
+        );
+    } else if !is_quiet() {
+        println!("Checked {} pages, no problems found", site.page_count());
+    }
+    exit_code(problems)
+}
+
+/// Lists the links and directories the walk left out, and why.
+fn print_skipped(site: &Site) {
+    if site.skipped().is_empty() || is_quiet() {
+        return;
+    }
+    println!("Skipped {} files and directories:", site.skipped().len());
+    for skipped in site.skipped() {
+        println!("  {:?}: {}", skipped.path, skipped.reason);
+    }
+}
+
+fn exit_code(failures: usize) -> ExitCode {
+    match failures {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
     }
 }
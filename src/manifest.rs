@@ -0,0 +1,210 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::read_to_string,
+    path::{Component, Path, PathBuf},
+};
+
+/// Where the manifest of the last build goes in the output.
+pub const MANIFEST_PATH: &str = "_wikiup/manifest.tsv";
+
+const HEADER: &str = "wikiup-manifest 3";
+
+/// What each page of the last build was rendered from, so unchanged pages
+/// can be skipped, and which files in the output the build owns, so stale
+/// ones can be removed. Saved as a header with the site hash, then a line of
+/// tab-separated fields per page and per copied file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Manifest {
+    /// A hash of everything every page depends on: the settings, the
+    /// navigation and the version of wikiup.
+    pub site: u64,
+    pub pages: BTreeMap<PathBuf, PageEntry>,
+    /// Assets and theme files copied, relative to the output directory.
+    pub assets: BTreeSet<PathBuf>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PageEntry {
+    /// A hash of the markdown and its last modified date.
+    pub source: u64,
+    /// A hash of the layout it went through.
+    pub template: u64,
+    /// The page written, relative to the output directory.
+    pub output: PathBuf,
+    /// The targets of its wiki links and the pages they resolved to.
+    pub links: Vec<(String, Option<PathBuf>)>,
+}
+
+impl Manifest {
+    /// Reads the manifest in the output directory. A missing or unreadable
+    /// one is empty, which rebuilds everything.
+    pub fn read(out: &Path) -> Self {
+        read_to_string(out.join(MANIFEST_PATH))
+            .ok()
+            .and_then(|text| Manifest::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Every file in the output the build wrote, other than the built-in
+    /// theme's, relative to the output directory.
+    pub fn outputs(&self) -> BTreeSet<PathBuf> {
+        self.pages
+            .values()
+            .map(|entry| entry.output.clone())
+            .chain(self.assets.iter().cloned())
+            // Only paths inside the output directory, in case the file was
+            // edited by hand.
+            .filter(|path| {
+                path.components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+            })
+            .collect()
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let site = lines.next()?.strip_prefix(HEADER)?.strip_prefix('\t')?;
+        let mut manifest = Manifest {
+            site: u64::from_str_radix(site, 16).ok()?,
+            ..Default::default()
+        };
+
+        for line in lines {
+            let mut fields = line.split('\t');
+            match fields.next()? {
+                "asset" => {
+                    manifest.assets.insert(PathBuf::from(fields.next()?));
+                    continue;
+                }
+                "page" => (),
+                _ => return None,
+            }
+            let (Some(path), Some(source), Some(template), Some(output)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return None;
+            };
+            let mut links = Vec::new();
+            while let Some(target) = fields.next() {
+                let resolved = fields.next()?;
+                links.push((
+                    target.to_string(),
+                    (!resolved.is_empty()).then(|| PathBuf::from(resolved)),
+                ));
+            }
+            manifest.pages.insert(
+                PathBuf::from(path),
+                PageEntry {
+                    source: u64::from_str_radix(source, 16).ok()?,
+                    template: u64::from_str_radix(template, 16).ok()?,
+                    output: PathBuf::from(output),
+                    links,
+                },
+            );
+        }
+        Some(manifest)
+    }
+
+    /// The manifest as text. Files with tabs or line breaks in their paths,
+    /// or pages with them in links, are left out: such pages are always
+    /// rebuilt and such files never removed.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{HEADER}\t{:016x}\n", self.site);
+        let mut push_line = |fields: &[String]| {
+            if !fields
+                .iter()
+                .any(|field| field.contains(['\t', '\n', '\r']))
+            {
+                text.push_str(&fields.join("\t"));
+                text.push('\n');
+            }
+        };
+        for (path, entry) in &self.pages {
+            let mut fields = vec![
+                "page".to_string(),
+                path.to_string_lossy().to_string(),
+                format!("{:016x}", entry.source),
+                format!("{:016x}", entry.template),
+                entry.output.to_string_lossy().to_string(),
+            ];
+            for (target, resolved) in &entry.links {
+                fields.push(target.clone());
+                fields.push(
+                    resolved
+                        .as_ref()
+                        .map(|path| path.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                );
+            }
+            push_line(&fields);
+        }
+        for path in &self.assets {
+            push_line(&["asset".to_string(), path.to_string_lossy().to_string()]);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let manifest = Manifest {
+            site: 0xabc,
+            pages: BTreeMap::from([
+                (
+                    PathBuf::from("guides/setup.md"),
+                    PageEntry {
+                        source: 1,
+                        template: u64::MAX,
+                        output: PathBuf::from("guides/setup.html"),
+                        links: vec![
+                            ("Home".into(), Some(PathBuf::from("index.md"))),
+                            ("Missing".into(), None),
+                        ],
+                    },
+                ),
+                (
+                    PathBuf::from("index.md"),
+                    PageEntry {
+                        source: 2,
+                        template: 3,
+                        output: PathBuf::from("index.html"),
+                        links: Vec::new(),
+                    },
+                ),
+            ]),
+            assets: BTreeSet::from([PathBuf::from("img/logo.png")]),
+        };
+        let text = manifest.to_text();
+        assert!(text.starts_with("wikiup-manifest 3\t0000000000000abc\npage\t"));
+        assert!(text.ends_with("\nasset\timg/logo.png\n"));
+        assert_eq!(Manifest::parse(&text), Some(manifest));
+
+        assert_eq!(Manifest::parse(""), None);
+        assert_eq!(Manifest::parse("wikiup-manifest 2\t0"), None);
+        assert_eq!(
+            Manifest::parse("wikiup-manifest 3\t0\npage\tindex.md\t1"),
+            None
+        );
+        assert_eq!(
+            Manifest::parse("wikiup-manifest 3\t0\nfile\tindex.md"),
+            None
+        );
+    }
+
+    #[test]
+    fn outputs() {
+        let manifest = Manifest {
+            assets: BTreeSet::from([
+                PathBuf::from("a.png"),
+                PathBuf::from("../escape.png"),
+                PathBuf::from("/etc/passwd"),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(manifest.outputs(), BTreeSet::from([PathBuf::from("a.png")]));
+    }
+}
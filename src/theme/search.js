@@ -0,0 +1,189 @@
+// Searches the index wikiup writes to `_wikiup/search-index.json`. Queries
+// are split into terms the way the index is, every term has to match, and
+// the last one also matches as a prefix so results show up while typing.
+(function () {
+  "use strict";
+
+  var input = document.getElementById("search-input");
+  var list = document.getElementById("search-results");
+  if (!input || !list) {
+    return;
+  }
+
+  var root = input.getAttribute("data-root") || "";
+  var maxResults = 10;
+  var k1 = 1.2;
+  var b = 0.75;
+  var index = null;
+  var terms = null;
+  var averageLength = 1;
+
+  function load() {
+    if (index) {
+      return Promise.resolve();
+    }
+    return fetch(root + "_wikiup/search-index.json")
+      .then(function (response) {
+        return response.json();
+      })
+      .then(function (json) {
+        index = json;
+        terms = Object.keys(index.terms).sort();
+        var total = 0;
+        index.sections.forEach(function (section) {
+          total += section[4];
+        });
+        averageLength = total / Math.max(index.sections.length, 1) || 1;
+      });
+  }
+
+  function tokenize(text) {
+    return text
+      .toLowerCase()
+      .split(/[^\p{Alphabetic}\p{N}]+/u)
+      .filter(function (term) {
+        return Array.from(term).length >= 2;
+      });
+  }
+
+  // Index terms equal to `term`, or starting with it when `prefix` is set.
+  function expand(term, prefix) {
+    if (!prefix) {
+      return index.terms.hasOwnProperty(term) ? [term] : [];
+    }
+    var low = 0;
+    var high = terms.length;
+    while (low < high) {
+      var middle = (low + high) >> 1;
+      if (terms[middle] < term) {
+        low = middle + 1;
+      } else {
+        high = middle;
+      }
+    }
+    var matches = [];
+    for (var i = low; i < terms.length && terms[i].indexOf(term) === 0; i++) {
+      matches.push(terms[i]);
+    }
+    return matches;
+  }
+
+  // Ranks sections with BM25. Prefix matches count half as much as the
+  // exact term.
+  function search(query) {
+    var queryTerms = tokenize(query);
+    var scores = null;
+    queryTerms.forEach(function (term, i) {
+      var termScores = {};
+      expand(term, i === queryTerms.length - 1).forEach(function (match) {
+        var postings = index.terms[match];
+        var frequency = postings[0];
+        var idf = Math.log(1 + (index.sections.length - frequency + 0.5) / (frequency + 0.5));
+        var weight = match === term ? 1 : 0.5;
+        for (var j = 1; j < postings.length; j += 2) {
+          var section = postings[j];
+          var tf = postings[j + 1];
+          var length = index.sections[section][4];
+          var score = idf * (tf * (k1 + 1)) / (tf + k1 * (1 - b + b * length / averageLength));
+          termScores[section] = (termScores[section] || 0) + weight * score;
+        }
+      });
+
+      if (scores === null) {
+        scores = termScores;
+      } else {
+        var both = {};
+        Object.keys(scores).forEach(function (section) {
+          if (termScores.hasOwnProperty(section)) {
+            both[section] = scores[section] + termScores[section];
+          }
+        });
+        scores = both;
+      }
+    });
+
+    return Object.keys(scores || {})
+      .map(function (section) {
+        return { section: index.sections[section], score: scores[section] };
+      })
+      .sort(function (a, b) {
+        return b.score - a.score;
+      })
+      .slice(0, maxResults);
+  }
+
+  function show(results, query) {
+    list.textContent = "";
+    results.forEach(function (result) {
+      var section = result.section;
+      var page = index.pages[section[0]];
+      var link = document.createElement("a");
+      link.href = root + page[0] + (section[1] ? "#" + section[1] : "");
+
+      var title = document.createElement("span");
+      title.className = "search-result-title";
+      title.textContent = section[2] === page[1] ? page[1] : page[1] + " › " + section[2];
+      link.appendChild(title);
+      if (section[3]) {
+        var excerpt = document.createElement("span");
+        excerpt.className = "search-result-excerpt";
+        excerpt.textContent = section[3];
+        link.appendChild(excerpt);
+      }
+
+      var item = document.createElement("li");
+      item.appendChild(link);
+      list.appendChild(item);
+    });
+    if (results.length === 0 && query) {
+      var empty = document.createElement("li");
+      empty.className = "search-no-results";
+      empty.textContent = "No results";
+      list.appendChild(empty);
+    }
+    list.hidden = !query;
+  }
+
+  function update() {
+    var query = input.value.trim();
+    if (!query) {
+      show([], "");
+      return;
+    }
+    load()
+      .then(function () {
+        if (input.value.trim() === query) {
+          show(search(query), query);
+        }
+      })
+      .catch(function () {
+        list.textContent = "";
+        var error = document.createElement("li");
+        error.className = "search-no-results";
+        error.textContent = "The search index couldn't be loaded";
+        list.appendChild(error);
+        list.hidden = false;
+      });
+  }
+
+  input.addEventListener("input", update);
+  input.addEventListener("focus", load);
+  input.addEventListener("keydown", function (event) {
+    if (event.key === "Enter") {
+      var first = list.querySelector("a");
+      if (first) {
+        window.location.href = first.href;
+      }
+    } else if (event.key === "Escape") {
+      input.value = "";
+      show([], "");
+    }
+  });
+  document.addEventListener("keydown", function (event) {
+    var typing = /^(INPUT|TEXTAREA|SELECT)$/.test(document.activeElement.tagName);
+    if (event.key === "/" && !typing) {
+      event.preventDefault();
+      input.focus();
+    }
+  });
+})();
@@ -0,0 +1,138 @@
+use crate::build::Site;
+use search::SearchIndex;
+use server::LiveReload;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread::sleep,
+    time::{Duration, Instant, SystemTime},
+};
+use util::{
+    io::{walk, WalkEntry},
+    log::is_quiet,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls the root for changed files, rebuilds what they affect and reloads
+/// open pages. Never returns.
+pub fn watch(
+    root: &Path,
+    mut site: Site,
+    search_index: Arc<RwLock<SearchIndex>>,
+    live_reload: Arc<LiveReload>,
+) {
+    if !is_quiet() {
+        println!("Watching {root:?} for changes");
+    }
+    let mut modified = modification_times(root, &site);
+    loop {
+        sleep(POLL_INTERVAL);
+
+        let current = modification_times(root, &site);
+        let changed = changed_paths(&modified, &current);
+        modified = current;
+        if changed.is_empty() {
+            continue;
+        }
+
+        let start = Instant::now();
+        let (new_index, pages) = site.rebuild(&changed);
+        // New settings can change which files are in the wiki, which the
+        // rebuild has already taken care of.
+        if changed.iter().any(|path| site.is_settings_file(path)) {
+            modified = modification_times(root, &site);
+        }
+        match search_index.write() {
+            Ok(mut search_index) => *search_index = new_index,
+            Err(_) => eprintln!("Failed to update the search index"),
+        }
+        live_reload.reload();
+        if !is_quiet() {
+            println!("Rebuilt {pages} pages in {:?}", start.elapsed());
+        }
+    }
+}
+
+/// When each file of the wiki under the root was last modified, along with
+/// the config and ignore files, which the walk leaves out.
+fn modification_times(root: &Path, site: &Site) -> HashMap<PathBuf, SystemTime> {
+    let entry_rx = walk(root.to_path_buf(), site.walk_options());
+    let mut times = HashMap::new();
+    while let Ok(entry) = entry_rx.recv() {
+        let paths = match entry {
+            WalkEntry::File(path) => vec![path],
+            WalkEntry::Dir(dir) => match dir.strip_prefix(root) {
+                Ok(relative) => site
+                    .settings_files(relative)
+                    .iter()
+                    .map(|path| root.join(path))
+                    .collect(),
+                Err(_) => continue,
+            },
+            WalkEntry::Skipped(_) => continue,
+        };
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            // Files can disappear between listing and reading their metadata.
+            if let Ok(time) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                times.insert(relative.to_path_buf(), time);
+            }
+        }
+    }
+    times
+}
+
+/// Paths that were added, removed or modified, sorted.
+fn changed_paths(
+    before: &HashMap<PathBuf, SystemTime>,
+    after: &HashMap<PathBuf, SystemTime>,
+) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = after
+        .iter()
+        .filter(|(path, time)| before.get(*path) != Some(time))
+        .map(|(path, _)| path.clone())
+        .chain(
+            before
+                .keys()
+                .filter(|path| !after.contains_key(*path))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let time = SystemTime::UNIX_EPOCH;
+        let later = time + Duration::from_secs(1);
+        let before = HashMap::from([
+            (PathBuf::from("a.md"), time),
+            (PathBuf::from("b.md"), time),
+            (PathBuf::from("c.png"), time),
+        ]);
+        let after = HashMap::from([
+            (PathBuf::from("a.md"), time),
+            (PathBuf::from("b.md"), later),
+            (PathBuf::from("d.md"), time),
+        ]);
+        assert_eq!(
+            changed_paths(&before, &after),
+            [
+                PathBuf::from("b.md"),
+                PathBuf::from("c.png"),
+                PathBuf::from("d.md")
+            ]
+        );
+        assert!(changed_paths(&after, &after).is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+use markdown::encode_path_segment;
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+/// Resolves `[[Page]]` links against the markdown files found under the root.
+/// Names are matched case-insensitively and spaces, dashes and underscores
+/// are treated as the same character, either against the full path relative
+/// to the root (`[[guides/Setup]]`) or against the file name alone
+/// (`[[Setup]]`).
+pub struct PageIndex {
+    pages: HashMap<String, PathBuf>,
+}
+
+impl PageIndex {
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let mut sorted = paths.to_vec();
+        sorted.sort();
+
+        let mut pages = HashMap::new();
+        for path in &sorted {
+            let without_extension = path.with_extension("");
+            pages.insert(
+                normalize(&without_extension.to_string_lossy()),
+                path.clone(),
+            );
+        }
+
+        // File names only fill the gaps, so a full path always wins and the
+        // shortest path wins between pages with the same name.
+        sorted.sort_by_key(|path| path.components().count());
+        for path in &sorted {
+            if let Some(stem) = path.file_stem() {
+                pages
+                    .entry(normalize(&stem.to_string_lossy()))
+                    .or_insert_with(|| path.clone());
+            }
+        }
+
+        Self { pages }
+    }
+
+    /// Lets `[[alias]]` link to the page at `path`. Aliases only fill gaps,
+    /// so they never take a name from a page that has it.
+    pub fn add_alias(&mut self, alias: &str, path: &Path) {
+        self.pages
+            .entry(normalize(alias))
+            .or_insert_with(|| path.to_path_buf());
+    }
+
+    /// Finds the page a link names, with or without the extension of its
+    /// file.
+    pub fn resolve(&self, page: &str) -> Option<&Path> {
+        let page = match page.rsplit_once('.') {
+            Some((stem, extension))
+                if ["md", "markdown"]
+                    .iter()
+                    .any(|markdown| extension.eq_ignore_ascii_case(markdown)) =>
+            {
+                stem
+            }
+            _ => page,
+        };
+        self.pages.get(&normalize(page)).map(PathBuf::as_path)
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim()
+        .trim_matches('/')
+        .chars()
+        .map(|c| match c {
+            ' ' | '_' => '-',
+            '\\' => '/',
+            c => c,
+        })
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Returns the URL of the HTML page generated for the markdown file `to`,
+/// relative to the HTML page generated for `from`. Both paths are relative to
+/// the root.
+pub fn relative_url(from: &Path, to: &Path) -> String {
+    let from_dir: Vec<Component> = match from.parent() {
+        Some(parent) => parent.components().collect(),
+        None => Vec::new(),
+    };
+    let to = to.with_extension("html");
+    let to: Vec<Component> = to.components().collect();
+
+    let common = from_dir
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from_dir.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|part| encode_path_segment(&part.as_os_str().to_string_lossy())),
+    );
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        let index = PageIndex::new(&[
+            PathBuf::from("index.md"),
+            PathBuf::from("guides/getting-started.md"),
+            PathBuf::from("guides/setup.md"),
+            PathBuf::from("setup.md"),
+        ]);
+        assert_eq!(
+            index.resolve("Getting Started"),
+            Some(Path::new("guides/getting-started.md"))
+        );
+        assert_eq!(index.resolve("SETUP"), Some(Path::new("setup.md")));
+        assert_eq!(
+            index.resolve("Guides/Setup"),
+            Some(Path::new("guides/setup.md"))
+        );
+        assert_eq!(index.resolve("index.md"), Some(Path::new("index.md")));
+        assert_eq!(index.resolve("Setup.MD"), Some(Path::new("setup.md")));
+        assert_eq!(index.resolve("setup.markdown"), Some(Path::new("setup.md")));
+        assert_eq!(index.resolve("setup.txt"), None);
+        assert_eq!(index.resolve("Missing"), None);
+    }
+
+    #[test]
+    fn aliases() {
+        let mut index = PageIndex::new(&[PathBuf::from("setup.md"), PathBuf::from("deploy.md")]);
+        index.add_alias("Installation", Path::new("setup.md"));
+        index.add_alias("setup", Path::new("deploy.md"));
+        assert_eq!(index.resolve("installation"), Some(Path::new("setup.md")));
+        assert_eq!(index.resolve("Setup"), Some(Path::new("setup.md")));
+    }
+
+    #[test]
+    fn relative_urls() {
+        assert_eq!(
+            relative_url(Path::new("index.md"), Path::new("guides/setup.md")),
+            "guides/setup.html"
+        );
+        assert_eq!(
+            relative_url(Path::new("guides/setup.md"), Path::new("index.md")),
+            "../index.html"
+        );
+        assert_eq!(
+            relative_url(Path::new("a/b/c.md"), Path::new("a/d/My Page.md")),
+            "../d/My%20Page.html"
+        );
+        assert_eq!(
+            relative_url(Path::new("index.md"), Path::new("notes/C# Notes?.md")),
+            "notes/C%23%20Notes%3F.html"
+        );
+        assert_eq!(
+            relative_url(Path::new("index.md"), Path::new("Café 100%.md")),
+            "Caf%C3%A9%20100%25.html"
+        );
+        assert_eq!(
+            relative_url(Path::new("a/b.md"), Path::new("a/c.md")),
+            "c.html"
+        );
+    }
+}